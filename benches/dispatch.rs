@@ -0,0 +1,85 @@
+//! Compares intersecting a list or BVH of one concrete type, where calls are
+//! dispatched statically, with the same objects behind `Box<dyn Hittable>`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use ray_trace::{Point3, Ray, Vec3};
+use ray_trace::bvh::BvhNode;
+use ray_trace::color::Color;
+use ray_trace::hittable::Hittable;
+use ray_trace::hittable_list::{HittableList, World};
+use ray_trace::material::Lambertian;
+use ray_trace::sphere::Sphere;
+use ray_trace::utils::{random_float_range, seed_rng};
+
+const SPHERE_COUNTS: [usize; 2] = [16, 512];
+const RAY_COUNT: usize = 1024;
+
+fn spheres(count: usize) -> Vec<Sphere<'static>> {
+    seed_rng(count as u64);
+    (0..count)
+        .map(|_| {
+            let center = Vec3::random_range(-10.0, 10.0);
+            let material = Box::new(Lambertian::new(Color::random()));
+            Sphere::new(center, random_float_range(0.2, 1.0), material)
+        })
+        .collect()
+}
+
+fn static_list(count: usize) -> HittableList<Sphere<'static>> {
+    spheres(count).into_iter().fold(HittableList::new(), |list, sphere| list.add(sphere))
+}
+
+fn dynamic_list(count: usize) -> World {
+    spheres(count).into_iter().fold(World::new(), |list, sphere| list.add(Box::new(sphere)))
+}
+
+fn rays() -> Vec<Ray> {
+    seed_rng(0);
+    (0..RAY_COUNT)
+        .map(|_| Ray::new(Point3::random_range(-12.0, 12.0), Vec3::random_unit_vector()))
+        .collect()
+}
+
+fn trace<H: Hittable>(world: &H, rays: &[Ray]) -> usize {
+    rays.iter()
+        .filter(|ray| world.hit(ray, 0.001, f32::INFINITY).is_hit)
+        .count()
+}
+
+fn bench_list(c: &mut Criterion) {
+    let rays = rays();
+    let mut group = c.benchmark_group("list");
+    for count in SPHERE_COUNTS {
+        let world = static_list(count);
+        group.bench_with_input(BenchmarkId::new("static", count), &world, |b, world| {
+            b.iter(|| trace(black_box(world), &rays))
+        });
+        let world = dynamic_list(count);
+        group.bench_with_input(BenchmarkId::new("dynamic", count), &world, |b, world| {
+            b.iter(|| trace(black_box(world), &rays))
+        });
+    }
+    group.finish();
+}
+
+fn bench_bvh(c: &mut Criterion) {
+    let rays = rays();
+    let mut group = c.benchmark_group("bvh");
+    for count in SPHERE_COUNTS {
+        let world = BvhNode::new(static_list(count));
+        group.bench_with_input(BenchmarkId::new("static", count), &world, |b, world| {
+            b.iter(|| trace(black_box(world), &rays))
+        });
+        let world = BvhNode::new(dynamic_list(count));
+        group.bench_with_input(BenchmarkId::new("dynamic", count), &world, |b, world| {
+            b.iter(|| trace(black_box(world), &rays))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_list, bench_bvh);
+criterion_main!(benches);
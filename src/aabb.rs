@@ -0,0 +1,69 @@
+use std::mem::swap;
+
+use crate::{Point3, Ray};
+
+#[derive(Clone)]
+pub struct Aabb {
+    pub minimum: Point3,
+    pub maximum: Point3,
+}
+
+impl Aabb {
+    pub fn new(minimum: Point3, maximum: Point3) -> Aabb {
+        Aabb { minimum, maximum }
+    }
+
+    /// A box that contains nothing, the identity for `surrounding_box`.
+    pub fn empty() -> Aabb {
+        Aabb {
+            minimum: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            maximum: Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn surrounding_box(box0: &Aabb, box1: &Aabb) -> Aabb {
+        Aabb {
+            minimum: Point3::new(
+                box0.minimum.x().min(box1.minimum.x()),
+                box0.minimum.y().min(box1.minimum.y()),
+                box0.minimum.z().min(box1.minimum.z()),
+            ),
+            maximum: Point3::new(
+                box0.maximum.x().max(box1.maximum.x()),
+                box0.maximum.y().max(box1.maximum.y()),
+                box0.maximum.z().max(box1.maximum.z()),
+            ),
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        (&self.minimum + &self.maximum) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = &self.maximum - &self.minimum;
+        if d.x() < 0.0 || d.y() < 0.0 || d.z() < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for a in 0..3 {
+            let inv_d = 1.0 / ray.dir[a];
+            let mut t0 = (self.minimum[a] - ray.orig[a]) * inv_d;
+            let mut t1 = (self.maximum[a] - ray.orig[a]) * inv_d;
+            if inv_d < 0.0 {
+                swap(&mut t0, &mut t1);
+            }
+            t_min = t0.max(t_min);
+            t_max = t1.min(t_max);
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
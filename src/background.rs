@@ -0,0 +1,54 @@
+use crate::Vec3;
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::ray::Ray;
+use crate::sky::Sky;
+
+/// The radiance arriving along rays that escape the scene.
+pub enum Background {
+    Solid(Color),
+    /// A vertical blend from `bottom`, straight down, to `top`, straight up.
+    Gradient { bottom: Color, top: Color },
+    Environment(EnvironmentMap),
+    Sky(Sky),
+}
+
+impl Background {
+    pub fn color(&self, ray: &Ray) -> Color {
+        match self {
+            Background::Solid(color) => color.clone(),
+            Background::Gradient { bottom, top } => {
+                let unit_direction = ray.dir.unit_vector();
+                let t = 0.5 * (unit_direction.y() + 1.0);
+                bottom * (1.0 - t) + top * t
+            }
+            Background::Environment(environment) => environment.radiance(&ray.dir),
+            Background::Sky(sky) => sky.radiance(&ray.dir),
+        }
+    }
+
+    /// The solid angle density with which `random_direction` picks `direction`.
+    /// Backgrounds that are not sampled directly have a density of zero.
+    pub fn pdf_value(&self, direction: &Vec3) -> f32 {
+        match self {
+            Background::Environment(environment) => environment.pdf_value(direction),
+            _ => 0.0,
+        }
+    }
+
+    pub fn random_direction(&self) -> Vec3 {
+        match self {
+            Background::Environment(environment) => environment.random_direction(),
+            _ => Vec3::new(1.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::Ray;
+
+// Relative costs used by the surface area heuristic.
+const TRAVERSAL_COST: f32 = 1.0;
+const INTERSECTION_COST: f32 = 1.0;
+
+pub enum BvhNode<H: Hittable> {
+    Leaf(H),
+    Branch {
+        left: Box<BvhNode<H>>,
+        right: Box<BvhNode<H>>,
+        bounding_box: Aabb,
+    },
+    Empty,
+}
+
+impl<H: Hittable> BvhNode<H> {
+    pub fn new(list: HittableList<H>) -> BvhNode<H> {
+        let objects = list.into_objects()
+            .into_iter()
+            .map(|object| {
+                let bounding_box = object.bounding_box();
+                (object, bounding_box)
+            })
+            .collect();
+
+        BvhNode::build(objects)
+    }
+
+    fn build(mut objects: Vec<(H, Aabb)>) -> BvhNode<H> {
+        match objects.len() {
+            0 => return BvhNode::Empty,
+            1 => return BvhNode::Leaf(objects.pop().unwrap().0),
+            _ => {}
+        }
+
+        let bounding_box = objects.iter()
+            .fold(Aabb::empty(), |accum, (_, object_box)| Aabb::surrounding_box(&accum, object_box));
+
+        let (axis, split) = find_sah_split(&mut objects, &bounding_box);
+        sort_by_centroid(&mut objects, axis);
+        let right_objects = objects.split_off(split);
+
+        BvhNode::Branch {
+            left: Box::new(BvhNode::build(objects)),
+            right: Box::new(BvhNode::build(right_objects)),
+            bounding_box,
+        }
+    }
+}
+
+impl<H: Hittable> Hittable for BvhNode<H> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
+        match self {
+            BvhNode::Leaf(object) => object.hit(ray, t_min, t_max),
+            BvhNode::Branch { left, right, bounding_box } => {
+                if !bounding_box.hit(ray, t_min, t_max) {
+                    return HitRecord::miss();
+                }
+
+                let left_rec = left.hit(ray, t_min, t_max);
+                let right_rec = right.hit(ray, t_min, if left_rec.is_hit { left_rec.t } else { t_max });
+
+                if right_rec.is_hit {
+                    right_rec
+                } else {
+                    left_rec
+                }
+            }
+            BvhNode::Empty => HitRecord::miss(),
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match self {
+            BvhNode::Leaf(object) => object.bounding_box(),
+            BvhNode::Branch { bounding_box, .. } => bounding_box.clone(),
+            BvhNode::Empty => Aabb::empty(),
+        }
+    }
+}
+
+fn sort_by_centroid<H>(objects: &mut [(H, Aabb)], axis: usize) {
+    objects.sort_by(|(_, a), (_, b)| a.centroid()[axis].total_cmp(&b.centroid()[axis]));
+}
+
+/// Sweeps the objects along every axis and returns the axis and split index
+/// that minimise the estimated cost of traversing the resulting children.
+fn find_sah_split<H>(objects: &mut [(H, Aabb)], bounding_box: &Aabb) -> (usize, usize) {
+    let n = objects.len();
+    let parent_area = bounding_box.surface_area();
+
+    let mut best_axis = 0;
+    let mut best_split = n / 2;
+    let mut best_cost = f32::INFINITY;
+
+    for axis in 0..3 {
+        sort_by_centroid(objects, axis);
+
+        // right_areas[i] is the area of the box around objects[i..].
+        let mut right_areas = vec![0.0; n];
+        let mut right_box = Aabb::empty();
+        for i in (1..n).rev() {
+            right_box = Aabb::surrounding_box(&right_box, &objects[i].1);
+            right_areas[i] = right_box.surface_area();
+        }
+
+        let mut left_box = Aabb::empty();
+        for split in 1..n {
+            left_box = Aabb::surrounding_box(&left_box, &objects[split - 1].1);
+            let cost = if parent_area > 0.0 {
+                TRAVERSAL_COST + INTERSECTION_COST
+                    * (left_box.surface_area() * split as f32 + right_areas[split] * (n - split) as f32)
+                    / parent_area
+            } else {
+                TRAVERSAL_COST + INTERSECTION_COST * n as f32
+            };
+
+            if cost < best_cost {
+                best_cost = cost;
+                best_axis = axis;
+                best_split = split;
+            }
+        }
+    }
+
+    (best_axis, best_split)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::{Point3, Vec3};
+    use crate::bvh::BvhNode;
+    use crate::hittable::Hittable;
+    use crate::hittable_list::HittableList;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::color::Color;
+
+    fn build_list(spheres: &[(Point3, f32)]) -> HittableList<Sphere<'static>> {
+        spheres.iter().fold(HittableList::new(), |list, (center, radius)| {
+            list.add(Sphere::new(center.clone(), *radius, Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))))
+        })
+    }
+
+    fn random_point(rng: &mut StdRng, extent: f32) -> Point3 {
+        Point3::new(rng.gen_range(-extent..extent), rng.gen_range(-extent..extent), rng.gen_range(-extent..extent))
+    }
+
+    #[test]
+    fn bvh_hits_match_linear_list() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let spheres: Vec<(Point3, f32)> = (0..200)
+            .map(|_| (random_point(&mut rng, 10.0), rng.gen_range(0.1..1.5)))
+            .collect();
+
+        let list = build_list(&spheres);
+        let bvh = BvhNode::new(build_list(&spheres));
+
+        for _ in 0..2000 {
+            let direction = loop {
+                let candidate = random_point(&mut rng, 1.0);
+                if candidate.length_squared() > 1e-4 && candidate.length_squared() < 1.0 {
+                    break candidate.unit_vector();
+                }
+            };
+            let ray = Ray::new(random_point(&mut rng, 15.0), direction);
+
+            let expected = list.hit(&ray, 0.001, f32::INFINITY);
+            let actual = bvh.hit(&ray, 0.001, f32::INFINITY);
+
+            assert_eq!(expected.is_hit, actual.is_hit);
+            if expected.is_hit {
+                assert!((expected.t - actual.t).abs() < 1e-4);
+                assert!((&expected.p - &actual.p).length() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_bvh_never_hits() {
+        let bvh = BvhNode::new(build_list(&[]));
+        let ray = Ray::new(Point3::origin(), Vec3::new(0.0, 0.0, -1.0));
+
+        assert!(!bvh.hit(&ray, 0.001, f32::INFINITY).is_hit);
+    }
+
+    #[test]
+    fn bvh_bounding_box_encloses_all_spheres() {
+        let spheres = vec![
+            (Point3::new(-3.0, 0.0, 0.0), 1.0),
+            (Point3::new(2.0, 4.0, 0.0), 0.5),
+            (Point3::new(0.0, 0.0, 5.0), 2.0),
+        ];
+        let bounding_box = BvhNode::new(build_list(&spheres)).bounding_box();
+
+        assert_eq!(bounding_box.minimum.x(), -4.0);
+        assert_eq!(bounding_box.maximum.y(), 4.5);
+        assert_eq!(bounding_box.maximum.z(), 7.0);
+    }
+}
@@ -52,4 +52,50 @@ impl Camera {
             &self.lower_left_corner + &self.horizontal * s + &self.vertical * t - &self.origin - offset,
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Point3, Vec3};
+    use crate::background::Background;
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::hittable_list::HittableList;
+    use crate::light::Lights;
+    use crate::material::DiffuseLight;
+    use crate::renderer::{ImageProperties, RenderProperties, Renderer};
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn images_are_the_right_way_round() {
+        let camera = Camera::new(
+            Point3::new(0.0, 0.0, 5.0),
+            Point3::origin(),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            5.0,
+        );
+        // A light at +x and above the middle, on a black background.
+        let world = HittableList::new()
+            .add(Sphere::new(Point3::new(1.0, 0.5, 0.0), 0.5, Box::new(DiffuseLight::new(Color::new(1.0, 1.0, 1.0)))));
+        let film = Renderer::new(
+            world,
+            Lights::new(HittableList::<Sphere>::new()),
+            camera,
+            Background::Solid(Color::origin()),
+            ImageProperties { image_width: 8, image_height: 8 },
+            RenderProperties { samples_per_pixel: 1, max_depth: 2, seed: 0, thread_count: Some(1) },
+        )
+        .render();
+
+        let lit: Vec<(usize, usize)> = film.pixels().iter().enumerate()
+            .filter(|(_, pixel)| pixel.mean().x() > 0.0)
+            .map(|(index, _)| (index % 8, index / 8))
+            .collect();
+        assert!(!lit.is_empty());
+        // Columns run left to right and rows top to bottom.
+        assert!(lit.iter().all(|(column, row)| *column >= 4 && *row < 4), "{:?}", lit);
+    }
+}
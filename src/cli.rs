@@ -0,0 +1,376 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use ray_trace::Color;
+use ray_trace::output::ImageFormat;
+use ray_trace::renderer::DEFAULT_SEED;
+use ray_trace::scenes::BuiltinScene;
+
+pub const USAGE: &str = "\
+Usage: ray-trace [OPTIONS]
+
+Options:
+  -o, --output <PATH>      Write the image to PATH instead of stdout
+  -f, --format <FORMAT>    Output format: ppm, ppm-ascii, pgm, png, png16, exr,
+                           exr-float or pfm [default: from the output extension,
+                           or ppm-ascii on stdout]
+      --scene <SCENE>      Built-in scene to render: sample or final [default: final]
+      --scene-file <PATH>  Render the scene described in a TOML file instead
+      --background <R,G,B> Replace the scene's background with a solid color
+  -W, --width <PIXELS>     Image width
+  -H, --height <PIXELS>    Image height
+      --aspect <RATIO>     Aspect ratio, as a number or W:H
+  -s, --spp <N>            Samples per pixel [default: 500]
+  -d, --max-depth <N>      Maximum ray bounces, at most 127 [default: 50]
+      --seed <N>           Seed for scene generation and sampling [default: 0]
+  -j, --threads <N>        Worker threads, at most 1024 [default: all cores]
+  -q, --quiet              Print nothing but errors
+  -v, --verbose            Print the render settings and timings
+  -h, --help               Print this help";
+
+/// The most pixels an image may have. A film pixel takes 28 bytes, so this
+/// keeps the film under 4 GB.
+const MAX_PIXELS: i64 = 1 << 27;
+
+const MAX_THREADS: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+pub enum SceneSource {
+    Builtin(BuiltinScene),
+    File(PathBuf),
+}
+
+pub struct Args {
+    pub output: Option<PathBuf>,
+    pub format: ImageFormat,
+    pub scene: SceneSource,
+    pub background: Option<Color>,
+    width: Option<i32>,
+    height: Option<i32>,
+    aspect_ratio: Option<f32>,
+    pub samples_per_pixel: i32,
+    pub max_depth: i8,
+    pub seed: u64,
+    pub thread_count: Option<usize>,
+    pub verbosity: Verbosity,
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    HelpRequested,
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue { option: String, value: String, expected: &'static str },
+    UnknownFormat(String),
+    UnknownScene(String),
+    UnknownExtension(PathBuf),
+    OverconstrainedSize,
+    ImageTooLarge { width: i32, height: i32 },
+    ConflictingVerbosity,
+    ConflictingScenes,
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::HelpRequested => write!(f, "help requested"),
+            CliError::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+            CliError::MissingValue(option) => write!(f, "option '{}' needs a value", option),
+            CliError::InvalidValue { option, value, expected } => {
+                write!(f, "invalid value '{}' for '{}': expected {}", value, option, expected)
+            }
+            CliError::UnknownFormat(format) => write!(f, "unknown output format '{}'", format),
+            CliError::UnknownScene(scene) => write!(f, "unknown scene '{}'", scene),
+            CliError::UnknownExtension(path) => write!(
+                f,
+                "cannot tell the output format from '{}', pass --format",
+                path.display()
+            ),
+            CliError::OverconstrainedSize => {
+                write!(f, "--width, --height and --aspect cannot all be given at once")
+            }
+            CliError::ImageTooLarge { width, height } => {
+                write!(f, "a {}x{} image has more than the {} pixels allowed", width, height, MAX_PIXELS)
+            }
+            CliError::ConflictingVerbosity => write!(f, "--quiet and --verbose cannot be combined"),
+            CliError::ConflictingScenes => write!(f, "--scene and --scene-file cannot be combined"),
+        }
+    }
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args, CliError> {
+        let mut output = None;
+        let mut format = None;
+        let mut scene = None;
+        let mut scene_file = None;
+        let mut background = None;
+        let mut width: Option<i32> = None;
+        let mut height: Option<i32> = None;
+        let mut aspect_ratio = None;
+        let mut samples_per_pixel = 500;
+        let mut max_depth = 50;
+        let mut seed = DEFAULT_SEED;
+        let mut thread_count = None;
+        let mut quiet = false;
+        let mut verbose = false;
+
+        while let Some(arg) = args.next() {
+            // Accept both "--option value" and "--option=value".
+            let (option, mut inline_value) = match arg.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => (option.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || inline_value.take()
+                .or_else(|| args.next())
+                .ok_or_else(|| CliError::MissingValue(option.clone()));
+
+            match option.as_str() {
+                "-h" | "--help" => return Err(CliError::HelpRequested),
+                "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+                "-f" | "--format" => {
+                    let name = value()?;
+                    format = Some(ImageFormat::from_name(&name).ok_or(CliError::UnknownFormat(name))?);
+                }
+                "--scene" => {
+                    let name = value()?;
+                    scene = Some(BuiltinScene::from_name(&name).ok_or(CliError::UnknownScene(name))?);
+                }
+                "--scene-file" => scene_file = Some(PathBuf::from(value()?)),
+                "--background" => background = Some(parse_color(&option, &value()?)?),
+                "-W" | "--width" => width = Some(parse_at_least(&option, &value()?, 2, "an integer of at least 2")?),
+                "-H" | "--height" => height = Some(parse_at_least(&option, &value()?, 2, "an integer of at least 2")?),
+                "--aspect" => aspect_ratio = Some(parse_aspect_ratio(&option, &value()?)?),
+                "-s" | "--spp" => samples_per_pixel = parse_at_least(&option, &value()?, 1, "a positive integer")?,
+                "-d" | "--max-depth" => max_depth = parse_at_least(&option, &value()?, 1, "an integer from 1 to 127")?,
+                "--seed" => seed = parse_at_least(&option, &value()?, 0, "a non-negative integer")?,
+                "-j" | "--threads" => {
+                    thread_count = Some(parse_between(&option, &value()?, 1, MAX_THREADS, "an integer from 1 to 1024")?)
+                }
+                "-q" | "--quiet" => quiet = true,
+                "-v" | "--verbose" => verbose = true,
+                _ => return Err(CliError::UnknownOption(arg)),
+            }
+
+            if let Some(value) = inline_value {
+                return Err(CliError::InvalidValue { option, value, expected: "no value" });
+            }
+        }
+
+        let verbosity = match (quiet, verbose) {
+            (true, true) => return Err(CliError::ConflictingVerbosity),
+            (true, false) => Verbosity::Quiet,
+            (false, true) => Verbosity::Verbose,
+            (false, false) => Verbosity::Normal,
+        };
+
+        let format = match (format, &output) {
+            (Some(format), _) => format,
+            (None, Some(path)) => ImageFormat::from_path(path)
+                .ok_or_else(|| CliError::UnknownExtension(path.clone()))?,
+            (None, None) => ImageFormat::PpmAscii,
+        };
+
+        if width.is_some() && height.is_some() && aspect_ratio.is_some() {
+            return Err(CliError::OverconstrainedSize);
+        }
+
+        let scene = match (scene, scene_file) {
+            (Some(_), Some(_)) => return Err(CliError::ConflictingScenes),
+            (None, Some(path)) => SceneSource::File(path),
+            (scene, None) => SceneSource::Builtin(scene.unwrap_or(BuiltinScene::Final)),
+        };
+
+        Ok(Args {
+            output,
+            format,
+            scene,
+            background,
+            width,
+            height,
+            aspect_ratio,
+            samples_per_pixel,
+            max_depth,
+            seed,
+            thread_count,
+            verbosity,
+        })
+    }
+}
+
+impl Args {
+    /// Resolves the image width, height and aspect ratio from the flags that
+    /// were given, falling back to the scene's defaults for the rest. Fails if
+    /// the image would have more than `MAX_PIXELS` pixels.
+    pub fn resolution(&self, default_aspect_ratio: f32, default_width: i32) -> Result<(i32, i32, f32), CliError> {
+        let (width, height, aspect_ratio) = match (self.width, self.height, self.aspect_ratio) {
+            (Some(width), Some(height), _) => (width, height, width as f32 / height as f32),
+            (Some(width), None, aspect_ratio) => {
+                let aspect_ratio = aspect_ratio.unwrap_or(default_aspect_ratio);
+                (width, image_dimension(width as f32 / aspect_ratio), aspect_ratio)
+            }
+            (None, Some(height), aspect_ratio) => {
+                let aspect_ratio = aspect_ratio.unwrap_or(default_aspect_ratio);
+                (image_dimension(height as f32 * aspect_ratio), height, aspect_ratio)
+            }
+            (None, None, aspect_ratio) => {
+                let aspect_ratio = aspect_ratio.unwrap_or(default_aspect_ratio);
+                (default_width, image_dimension(default_width as f32 / aspect_ratio), aspect_ratio)
+            }
+        };
+
+        if width as i64 * height as i64 > MAX_PIXELS {
+            return Err(CliError::ImageTooLarge { width, height });
+        }
+        Ok((width, height, aspect_ratio))
+    }
+}
+
+fn image_dimension(size: f32) -> i32 {
+    (size as i32).max(2)
+}
+
+fn parse_at_least<T: FromStr + PartialOrd>(option: &str, value: &str, min: T, expected: &'static str) -> Result<T, CliError> {
+    match value.parse::<T>() {
+        Ok(parsed) if parsed >= min => Ok(parsed),
+        _ => Err(CliError::InvalidValue {
+            option: option.to_string(),
+            value: value.to_string(),
+            expected,
+        }),
+    }
+}
+
+fn parse_between<T: FromStr + PartialOrd>(option: &str, value: &str, min: T, max: T, expected: &'static str) -> Result<T, CliError> {
+    match parse_at_least(option, value, min, expected)? {
+        parsed if parsed <= max => Ok(parsed),
+        _ => Err(CliError::InvalidValue {
+            option: option.to_string(),
+            value: value.to_string(),
+            expected,
+        }),
+    }
+}
+
+fn parse_aspect_ratio(option: &str, value: &str) -> Result<f32, CliError> {
+    let aspect_ratio = match value.split_once(':') {
+        Some((width, height)) => match (width.parse::<f32>(), height.parse::<f32>()) {
+            (Ok(width), Ok(height)) => width / height,
+            _ => f32::NAN,
+        },
+        None => value.parse::<f32>().unwrap_or(f32::NAN),
+    };
+
+    if aspect_ratio.is_finite() && aspect_ratio > 0.0 {
+        Ok(aspect_ratio)
+    } else {
+        Err(CliError::InvalidValue {
+            option: option.to_string(),
+            value: value.to_string(),
+            expected: "a positive number or W:H",
+        })
+    }
+}
+
+fn parse_color(option: &str, value: &str) -> Result<Color, CliError> {
+    let channels: Vec<f32> = value.split(',')
+        .map(|channel| channel.trim().parse::<f32>().unwrap_or(f32::NAN))
+        .collect();
+
+    match channels[..] {
+        [r, g, b] if channels.iter().all(|channel| channel.is_finite() && *channel >= 0.0) => Ok(Color::new(r, g, b)),
+        _ => Err(CliError::InvalidValue {
+            option: option.to_string(),
+            value: value.to_string(),
+            expected: "three non-negative numbers as R,G,B",
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{Args, CliError};
+
+    fn parse(args: &[&str]) -> Result<Args, CliError> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn invalid_value(args: &[&str]) -> (String, String) {
+        match parse(args) {
+            Err(CliError::InvalidValue { option, value, .. }) => (option, value),
+            Err(error) => panic!("expected an invalid value, got '{}'", error),
+            Ok(_) => panic!("expected an invalid value, got none"),
+        }
+    }
+
+    #[test]
+    fn bad_values_name_their_option() {
+        assert_eq!(invalid_value(&["-W", "wide"]), ("-W".to_string(), "wide".to_string()));
+        assert_eq!(invalid_value(&["--width=1"]), ("--width".to_string(), "1".to_string()));
+        assert_eq!(invalid_value(&["--aspect", "16:0"]), ("--aspect".to_string(), "16:0".to_string()));
+        assert_eq!(invalid_value(&["--aspect", "16x9"]), ("--aspect".to_string(), "16x9".to_string()));
+        assert_eq!(invalid_value(&["--background", "0.1,0.2"]), ("--background".to_string(), "0.1,0.2".to_string()));
+        assert_eq!(invalid_value(&["--background", "1,1,1,1"]), ("--background".to_string(), "1,1,1,1".to_string()));
+        assert_eq!(invalid_value(&["--quiet=yes"]), ("--quiet".to_string(), "yes".to_string()));
+        assert_eq!(invalid_value(&["-j", "0"]), ("-j".to_string(), "0".to_string()));
+        assert_eq!(invalid_value(&["-j", "1000000"]), ("-j".to_string(), "1000000".to_string()));
+    }
+
+    #[test]
+    fn unknown_flags_and_missing_values_are_reported() {
+        assert!(matches!(parse(&["--colour"]), Err(CliError::UnknownOption(option)) if option == "--colour"));
+        assert!(matches!(parse(&["-s", "4", "-o"]), Err(CliError::MissingValue(option)) if option == "-o"));
+        assert!(matches!(parse(&["--scene", "teapot"]), Err(CliError::UnknownScene(_))));
+        assert!(matches!(parse(&["-q", "-v"]), Err(CliError::ConflictingVerbosity)));
+        assert!(matches!(parse(&["-W", "4", "-H", "4", "--aspect", "1"]), Err(CliError::OverconstrainedSize)));
+    }
+
+    #[test]
+    fn values_parse() {
+        let args = parse(&["--aspect", "16:9", "--background=0.5, 0.25, 1", "-j", "3"]).unwrap();
+        assert_eq!(args.aspect_ratio, Some(16.0 / 9.0));
+        assert_eq!(args.background.as_ref().map(|color| color.y()), Some(0.25));
+        assert_eq!(args.thread_count, Some(3));
+    }
+
+    #[test]
+    fn missing_dimensions_follow_from_the_others() {
+        let resolution = |args: &[&str]| parse(args).unwrap().resolution(1.5, 300).unwrap();
+
+        assert_eq!(resolution(&[]), (300, 200, 1.5));
+        assert_eq!(resolution(&["-W", "90"]), (90, 60, 1.5));
+        assert_eq!(resolution(&["-H", "90"]), (135, 90, 1.5));
+        assert_eq!(resolution(&["--aspect", "2:1"]), (300, 150, 2.0));
+        assert_eq!(resolution(&["-W", "100", "--aspect", "4"]), (100, 25, 4.0));
+        assert_eq!(resolution(&["-H", "100", "--aspect", "0.5"]), (50, 100, 0.5));
+        assert_eq!(resolution(&["-W", "100", "-H", "40"]), (100, 40, 2.5));
+        // Neither side drops below two pixels.
+        assert_eq!(resolution(&["-W", "4", "--aspect", "10"]), (4, 2, 10.0));
+    }
+
+    #[test]
+    fn images_with_too_many_pixels_are_errors() {
+        let resolution = |args: &[&str]| parse(args).unwrap().resolution(1.5, 300);
+
+        assert!(matches!(
+            resolution(&["-W", "100000", "-H", "100000"]),
+            Err(CliError::ImageTooLarge { width: 100000, height: 100000 })
+        ));
+        // A derived side counts too.
+        assert!(matches!(resolution(&["-W", "2000000000", "--aspect", "0.5"]), Err(CliError::ImageTooLarge { .. })));
+        assert!(matches!(
+            resolution(&["-W", "40000", "-H", "40000"]),
+            Err(CliError::ImageTooLarge { width: 40000, height: 40000 })
+        ));
+        assert!(resolution(&["-W", "16384", "-H", "8192"]).is_ok());
+        assert!(resolution(&["-W", "16384", "-H", "8193"]).is_err());
+    }
+}
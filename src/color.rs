@@ -4,20 +4,39 @@ use crate::Vec3;
 pub type Color = Vec3;
 
 impl Color {
-    pub fn write_color(&self, sample_per_pixel: i32) {
-        let mut r = self.x();
-        let mut g = self.y();
-        let mut b = self.z();
+    /// The relative luminance of a linear Rec. 709 color.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.x() + 0.7152 * self.y() + 0.0722 * self.z()
+    }
+
+    /// Applies gamma 2 to a linear color and quantises it to 8 bits per channel.
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        [
+            (256.0 * clamp(self.x().sqrt(), 0.0, 0.999)) as u8,
+            (256.0 * clamp(self.y().sqrt(), 0.0, 0.999)) as u8,
+            (256.0 * clamp(self.z().sqrt(), 0.0, 0.999)) as u8,
+        ]
+    }
 
-        let scale = 1.0 / (sample_per_pixel as f32);
-        r = (scale * r).sqrt();
-        g = (scale * g).sqrt();
-        b = (scale * b).sqrt();
+    /// Applies gamma 2 to a linear color and quantises it to 16 bits per channel.
+    pub fn to_rgb16(&self) -> [u16; 3] {
+        [
+            (65535.0 * clamp(self.x().sqrt(), 0.0, 1.0)).round() as u16,
+            (65535.0 * clamp(self.y().sqrt(), 0.0, 1.0)).round() as u16,
+            (65535.0 * clamp(self.z().sqrt(), 0.0, 1.0)).round() as u16,
+        ]
+    }
 
-        println!("{} {} {}",
-                 (256.0 * clamp(r, 0.0, 0.999)) as i32,
-                 (256.0 * clamp(g, 0.0, 0.999)) as i32,
-                 (256.0 * clamp(b, 0.0, 0.999)) as i32
-        )
+    /// Decodes a color stored with the sRGB transfer curve, as in most 8-bit
+    /// images, to linear values.
+    pub fn srgb_to_linear(&self) -> Color {
+        let decode = |value: f32| {
+            if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        Color::new(decode(self.x()), decode(self.y()), decode(self.z()))
     }
 }
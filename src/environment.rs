@@ -0,0 +1,205 @@
+use std::f32::consts::PI;
+use std::io;
+use std::path::Path;
+
+use crate::Vec3;
+use crate::color::Color;
+use crate::image::{Image, load_image};
+use crate::utils::{clamp, degrees_to_radians, random_float};
+
+/// Light arriving from infinitely far away, looked up in an equirectangular
+/// image. The top row of the image is straight up and its centre column looks
+/// down the negative z axis.
+///
+/// Directions are importance sampled in proportion to the luminance they carry,
+/// so bright regions such as the sun light the scene with little noise.
+pub struct EnvironmentMap {
+    image: Image,
+    rotation: f32,
+    intensity: f32,
+    // Chooses a row, then a column within it.
+    rows: Distribution1D,
+    columns: Vec<Distribution1D>,
+}
+
+impl EnvironmentMap {
+    /// `rotation` turns the map about the vertical axis, in degrees, and
+    /// `intensity` scales its radiance.
+    pub fn new(image: Image, rotation: f32, intensity: f32) -> EnvironmentMap {
+        let width = image.width();
+        let height = image.height();
+
+        let columns: Vec<Distribution1D> = (0..height)
+            .map(|y| {
+                // Rows near the poles cover less of the sphere.
+                let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+                Distribution1D::new((0..width).map(|x| image.pixel(x, y).luminance().max(0.0) * sin_theta).collect())
+            })
+            .collect();
+        let rows = Distribution1D::new(columns.iter().map(|row| row.integral).collect());
+
+        EnvironmentMap {
+            image,
+            rotation: degrees_to_radians(rotation),
+            intensity,
+            rows,
+            columns,
+        }
+    }
+
+    /// Loads the map from an image file, normally a Radiance HDR (.hdr) or
+    /// Portable Float Map (.pfm) image.
+    pub fn load(path: &Path, rotation: f32, intensity: f32) -> io::Result<EnvironmentMap> {
+        Ok(EnvironmentMap::new(load_image(path)?, rotation, intensity))
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        let (x, y) = self.pixel_at(direction);
+        self.image.pixel(x, y) * self.intensity
+    }
+
+    /// The solid angle density with which `random_direction` picks `direction`.
+    pub fn pdf_value(&self, direction: &Vec3) -> f32 {
+        let (u, v) = self.to_uv(&direction.unit_vector());
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let (x, y) = self.uv_to_pixel(u, v);
+        let uv_pdf = self.rows.pdf(y) * self.columns[y].pdf(x);
+        uv_pdf / (2.0 * PI * PI * sin_theta)
+    }
+
+    pub fn random_direction(&self) -> Vec3 {
+        let (v, y) = self.rows.sample(random_float());
+        let (u, _) = self.columns[y].sample(random_float());
+        self.direction_at(u, v)
+    }
+
+    fn pixel_at(&self, direction: &Vec3) -> (usize, usize) {
+        let (u, v) = self.to_uv(&direction.unit_vector());
+        self.uv_to_pixel(u, v)
+    }
+
+    fn uv_to_pixel(&self, u: f32, v: f32) -> (usize, usize) {
+        let x = ((u * self.image.width() as f32) as usize).min(self.image.width() - 1);
+        let y = ((v * self.image.height() as f32) as usize).min(self.image.height() - 1);
+        (x, y)
+    }
+
+    fn to_uv(&self, direction: &Vec3) -> (f32, f32) {
+        let theta = clamp(direction.y(), -1.0, 1.0).acos();
+        let phi = (-direction.x()).atan2(direction.z());
+        (((phi - self.rotation) / (2.0 * PI)).rem_euclid(1.0), theta / PI)
+    }
+
+    fn direction_at(&self, u: f32, v: f32) -> Vec3 {
+        let theta = PI * v;
+        let phi = 2.0 * PI * u + self.rotation;
+        Vec3::new(-theta.sin() * phi.sin(), theta.cos(), theta.sin() * phi.cos())
+    }
+}
+
+/// A piecewise constant distribution over [0, 1).
+struct Distribution1D {
+    function: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    fn new(function: Vec<f32>) -> Distribution1D {
+        let n = function.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + function[i] / n as f32;
+        }
+        let integral = cdf[n];
+
+        // A function that is zero everywhere is sampled uniformly instead.
+        for (i, value) in cdf.iter_mut().enumerate() {
+            *value = if integral > 0.0 { *value / integral } else { i as f32 / n as f32 };
+        }
+
+        Distribution1D { function, cdf, integral }
+    }
+
+    fn pdf(&self, index: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.function[index] / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns a sample in [0, 1) and the index of the piece it lies in.
+    fn sample(&self, u: f32) -> (f32, usize) {
+        let n = self.function.len();
+        let index = (self.cdf.partition_point(|value| *value <= u).max(1) - 1).min(n - 1);
+
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { clamp((u - self.cdf[index]) / width, 0.0, 1.0) } else { 0.5 };
+        (((index as f32 + offset) / n as f32).min(1.0 - f32::EPSILON), index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::Vec3;
+    use crate::color::Color;
+    use crate::environment::EnvironmentMap;
+    use crate::image::Image;
+    use crate::utils::seed_rng;
+
+    fn environment() -> EnvironmentMap {
+        let (width, height) = (32, 16);
+        let pixels = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                if (x, y) == (20, 5) {
+                    Color::new(50.0, 40.0, 30.0)
+                } else {
+                    Color::new(0.2, 0.3, 0.5) * (1.0 + y as f32 / 4.0)
+                }
+            })
+            .collect();
+        EnvironmentMap::new(Image::new(width, height, pixels), 30.0, 2.0)
+    }
+
+    #[test]
+    fn sampled_directions_round_trip_to_their_density() {
+        seed_rng(3);
+        let environment = environment();
+        let samples = 200_000;
+
+        // Importance sampling and uniform sampling of the sphere must agree on
+        // the total radiance of the map.
+        let mut importance = Color::origin();
+        let mut uniform = Color::origin();
+        for _ in 0..samples {
+            // Directions right at a pole have no density, as in the renderer.
+            let direction = environment.random_direction();
+            let pdf = environment.pdf_value(&direction);
+            if pdf > 0.0 {
+                importance += environment.radiance(&direction) / pdf;
+            }
+
+            let direction = Vec3::random_unit_vector();
+            uniform += environment.radiance(&direction) * (4.0 * PI);
+        }
+        let importance = importance / samples as f32;
+        let uniform = uniform / samples as f32;
+
+        for channel in 0..3 {
+            let relative_error = (importance[channel] - uniform[channel]).abs() / uniform[channel];
+            assert!(
+                relative_error < 0.05,
+                "channel {}: importance sampled {} against uniform {}",
+                channel, importance[channel], uniform[channel]
+            );
+        }
+    }
+}
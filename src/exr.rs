@@ -0,0 +1,226 @@
+use std::io;
+use std::io::Write;
+
+use half::f16;
+
+use crate::film::Film;
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const VERSION: [u8; 4] = [2, 0, 0, 0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrPixelType {
+    Half,
+    Float,
+}
+
+impl ExrPixelType {
+    fn id(self) -> i32 {
+        match self {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ExrPixelType::Half => 2,
+            ExrPixelType::Float => 4,
+        }
+    }
+}
+
+/// Writes the film's linear radiance as an uncompressed, single part scanline
+/// OpenEXR image with R, G and B channels.
+pub fn write_exr<W: Write>(film: &Film, pixel_type: ExrPixelType, out: &mut W) -> io::Result<()> {
+    let width = film.width();
+    let height = film.height();
+
+    let header = exr_header(width, height, pixel_type);
+    let line_size = 3 * width as usize * pixel_type.size();
+    // Each block holds one scanline, prefixed by its y coordinate and data size.
+    let block_size = 8 + line_size;
+    let first_block = header.len() + 8 * height as usize;
+
+    out.write_all(&header)?;
+    for y in 0..height as usize {
+        out.write_all(&((first_block + y * block_size) as u64).to_le_bytes())?;
+    }
+
+    let mut line = Vec::with_capacity(line_size);
+    for y in 0..height {
+        line.clear();
+        // Channels are stored in alphabetical order, each as a run over the scanline.
+        for channel in [2, 1, 0] {
+            for x in 0..width {
+                let value = film.color(x, y)[channel];
+                match pixel_type {
+                    ExrPixelType::Half => line.extend_from_slice(&f16::from_f32(value).to_le_bytes()),
+                    ExrPixelType::Float => line.extend_from_slice(&value.to_le_bytes()),
+                }
+            }
+        }
+
+        out.write_all(&y.to_le_bytes())?;
+        out.write_all(&(line_size as i32).to_le_bytes())?;
+        out.write_all(&line)?;
+    }
+
+    Ok(())
+}
+
+fn exr_header(width: i32, height: i32, pixel_type: ExrPixelType) -> Vec<u8> {
+    let mut header = vec![];
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION);
+
+    let mut channels = vec![];
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&pixel_type.id().to_le_bytes());
+        // pLinear and three reserved bytes, then the x and y sampling rates.
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        channels.extend_from_slice(&1i32.to_le_bytes());
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channels.push(0);
+    write_attribute(&mut header, "channels", "chlist", &channels);
+
+    // No compression.
+    write_attribute(&mut header, "compression", "compression", &[0]);
+
+    let window: Vec<u8> = [0, 0, width - 1, height - 1].iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+
+    // Increasing y.
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(&mut header, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(&mut header, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+
+    header.push(0);
+    header
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, attribute_type: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(attribute_type.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use half::f16;
+
+    use crate::color::Color;
+    use crate::exr::{write_exr, ExrPixelType};
+    use crate::film::Film;
+
+    fn film() -> Film {
+        let mut film = Film::new(2, 2);
+        film.add_sample(1, 0, Color::new(1.0, 2.0, 3.0));
+        film.add_sample(0, 1, Color::new(0.5, 0.0, 0.0));
+        film
+    }
+
+    fn read_i32(data: &[u8], at: usize) -> i32 {
+        i32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    fn read_u64(data: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+    }
+
+    // Splits the header into (name, type, value) attributes, returning them
+    // with the offset just past the header's terminating null.
+    fn attributes(data: &[u8]) -> (Vec<(String, String, Vec<u8>)>, usize) {
+        let mut attributes = vec![];
+        let mut at = 8;
+        let string = |at: &mut usize| {
+            let end = *at + data[*at..].iter().position(|byte| *byte == 0).unwrap();
+            let string = String::from_utf8(data[*at..end].to_vec()).unwrap();
+            *at = end + 1;
+            string
+        };
+        while data[at] != 0 {
+            let name = string(&mut at);
+            let attribute_type = string(&mut at);
+            let size = read_i32(data, at) as usize;
+            attributes.push((name, attribute_type, data[at + 4..at + 4 + size].to_vec()));
+            at += 4 + size;
+        }
+        (attributes, at + 1)
+    }
+
+    fn check_layout(pixel_type: ExrPixelType, pixel_type_id: i32, value_size: usize) -> Vec<u8> {
+        let mut data = vec![];
+        write_exr(&film(), pixel_type, &mut data).unwrap();
+
+        assert_eq!(&data[..4], &[0x76, 0x2f, 0x31, 0x01]);
+        assert_eq!(&data[4..8], &[2, 0, 0, 0]);
+
+        let (attributes, header_end) = attributes(&data);
+        let names: Vec<(&str, &str)> = attributes.iter().map(|(name, kind, _)| (name.as_str(), kind.as_str())).collect();
+        assert_eq!(names, [
+            ("channels", "chlist"),
+            ("compression", "compression"),
+            ("dataWindow", "box2i"),
+            ("displayWindow", "box2i"),
+            ("lineOrder", "lineOrder"),
+            ("pixelAspectRatio", "float"),
+            ("screenWindowCenter", "v2f"),
+            ("screenWindowWidth", "float"),
+        ]);
+
+        // Three channels of 18 bytes each, then the list's null.
+        let channels = &attributes[0].2;
+        assert_eq!(channels.len(), 3 * 18 + 1);
+        for (i, name) in [b'B', b'G', b'R'].iter().enumerate() {
+            let channel = &channels[18 * i..18 * (i + 1)];
+            assert_eq!(&channel[..2], &[*name, 0]);
+            assert_eq!(read_i32(channel, 2), pixel_type_id);
+            assert_eq!(read_i32(channel, 10), 1);
+            assert_eq!(read_i32(channel, 14), 1);
+        }
+        assert_eq!(attributes[1].2, [0]);
+        let window: Vec<i32> = (0..4).map(|i| read_i32(&attributes[2].2, 4 * i)).collect();
+        assert_eq!(window, [0, 0, 1, 1]);
+        assert_eq!(attributes[3].2, attributes[2].2);
+
+        // One offset per scanline, each pointing at its block: the y
+        // coordinate, the data size, then B, G and R runs over the line.
+        let line_size = 3 * 2 * value_size;
+        let first_block = header_end + 2 * 8;
+        assert_eq!(read_u64(&data, header_end), first_block as u64);
+        assert_eq!(read_u64(&data, header_end + 8), (first_block + 8 + line_size) as u64);
+        for y in 0..2 {
+            let block = first_block + y * (8 + line_size);
+            assert_eq!(read_i32(&data, block), y as i32);
+            assert_eq!(read_i32(&data, block + 4), line_size as i32);
+        }
+        assert_eq!(data.len(), first_block + 2 * (8 + line_size));
+
+        data[first_block + 8..first_block + 8 + line_size].to_vec()
+    }
+
+    #[test]
+    fn half_images_have_header_offsets_and_planar_scanlines() {
+        let line = check_layout(ExrPixelType::Half, 1, 2);
+        let values: Vec<f32> = line.chunks_exact(2).map(|bytes| f16::from_le_bytes([bytes[0], bytes[1]]).to_f32()).collect();
+        assert_eq!(values, [0.0, 3.0, 0.0, 2.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn float_images_have_header_offsets_and_planar_scanlines() {
+        let line = check_layout(ExrPixelType::Float, 2, 4);
+        let values: Vec<f32> = line.chunks_exact(4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap())).collect();
+        assert_eq!(values, [0.0, 3.0, 0.0, 2.0, 0.0, 1.0]);
+    }
+}
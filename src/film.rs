@@ -0,0 +1,128 @@
+use crate::color::Color;
+
+/// The radiance gathered for one pixel, kept linear and unclamped.
+#[derive(Clone)]
+pub struct FilmPixel {
+    pub sum: Color,
+    pub sum_squared: Color,
+    pub sample_count: u32,
+}
+
+impl FilmPixel {
+    pub fn new() -> FilmPixel {
+        FilmPixel {
+            sum: Color::origin(),
+            sum_squared: Color::origin(),
+            sample_count: 0,
+        }
+    }
+
+    pub fn add_sample(&mut self, sample: Color) {
+        self.sum_squared += sample.clone() * sample.clone();
+        self.sum += sample;
+        self.sample_count += 1;
+    }
+
+    pub fn mean(&self) -> Color {
+        if self.sample_count == 0 {
+            return Color::origin();
+        }
+        &self.sum / self.sample_count as f32
+    }
+
+    /// The per channel sample variance of the pixel's radiance.
+    pub fn variance(&self) -> Color {
+        if self.sample_count < 2 {
+            return Color::origin();
+        }
+        let n = self.sample_count as f32;
+        let mean = self.mean();
+        // Rounding can leave a tiny negative where the samples barely differ.
+        let variance = (&self.sum_squared - mean.clone() * mean * n) / (n - 1.0);
+        Color::new(variance.x().max(0.0), variance.y().max(0.0), variance.z().max(0.0))
+    }
+}
+
+impl Default for FilmPixel {
+    fn default() -> Self {
+        FilmPixel::new()
+    }
+}
+
+/// An in-memory framebuffer of linear radiance, stored row by row from the top
+/// scanline down.
+pub struct Film {
+    width: i32,
+    height: i32,
+    pixels: Vec<FilmPixel>,
+}
+
+impl Film {
+    pub fn new(width: i32, height: i32) -> Film {
+        Film {
+            width,
+            height,
+            pixels: vec![FilmPixel::new(); (width * height) as usize],
+        }
+    }
+
+    pub fn from_pixels(width: i32, height: i32, pixels: Vec<FilmPixel>) -> Film {
+        assert_eq!(pixels.len(), (width * height) as usize, "pixel count does not match film size");
+        Film { width, height, pixels }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> &FilmPixel {
+        &self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn add_sample(&mut self, x: i32, y: i32, sample: Color) {
+        self.pixels[(y * self.width + x) as usize].add_sample(sample)
+    }
+
+    /// The mean radiance of a pixel.
+    pub fn color(&self, x: i32, y: i32) -> Color {
+        self.pixel(x, y).mean()
+    }
+
+    pub fn pixels(&self) -> &[FilmPixel] {
+        &self.pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+    use crate::film::FilmPixel;
+
+    #[test]
+    fn pixels_give_mean_and_sample_variance() {
+        let mut pixel = FilmPixel::new();
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            pixel.add_sample(Color::new(value, 2.0 * value, 0.5));
+        }
+
+        let (mean, variance) = (pixel.mean(), pixel.variance());
+        assert!((&mean - &Color::new(2.5, 5.0, 0.5)).length() < 1e-6);
+        assert!((variance.x() - 5.0 / 3.0).abs() < 1e-5);
+        assert!((variance.y() - 20.0 / 3.0).abs() < 1e-5);
+        assert_eq!(variance.z(), 0.0);
+    }
+
+    #[test]
+    fn variance_is_never_negative() {
+        let mut pixel = FilmPixel::new();
+        for _ in 0..7 {
+            pixel.add_sample(Color::new(0.1, 0.3, 1e3));
+        }
+        let variance = pixel.variance();
+        assert!(variance.x() >= 0.0 && variance.y() >= 0.0 && variance.z() >= 0.0);
+    }
+}
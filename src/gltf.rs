@@ -0,0 +1,505 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ::gltf::{Gltf, Node};
+use ::gltf::camera::Projection;
+use ::gltf::mesh::Mode;
+use ::gltf::texture::{MagFilter, WrappingMode};
+
+use crate::{Point3, Vec3};
+use crate::color::Color;
+use crate::image::Image;
+use crate::jpeg::read_jpeg;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::normal_map::NormalMapped;
+use crate::png::read_png;
+use crate::scene_file::CameraDescription;
+use crate::texture::{ChannelTexture, Filter, ImageTexture, ScaledTexture, SolidColor, Texture, WrapMode};
+use crate::transform::Matrix4;
+use crate::triangle_mesh::TriangleMesh;
+
+/// Used for cameras that leave their aspect ratio to the viewer.
+const DEFAULT_ASPECT_RATIO: f32 = 16.0 / 9.0;
+
+/// The meshes and cameras of a glTF scene, with every node's transform already
+/// applied.
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    /// Perspective cameras, in the order they are found walking the node tree.
+    pub cameras: Vec<CameraDescription>,
+}
+
+/// A single primitive of a glTF mesh.
+pub struct GltfMesh {
+    /// The node's name, or the mesh's if the node has none.
+    pub name: String,
+    pub mesh: TriangleMesh<'static>,
+}
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Invalid {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl Display for GltfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io { path, error } => write!(f, "cannot read '{}': {}", path.display(), error),
+            GltfError::Invalid { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl Error for GltfError {}
+
+/// Loads the default scene of a .gltf or .glb file. Buffers and images must
+/// be embedded, or be local files next to the asset; nothing is fetched.
+///
+/// Metallic-roughness materials map onto the renderer's materials: emissive
+/// ones become a `DiffuseLight`, transmissive ones a `Dielectric`, mostly
+/// metallic ones a `Metal` blurred by their roughness, and the rest a
+/// `Lambertian`. Base colour, roughness and emissive textures are sampled
+/// across the surface, and normal textures bend its shading normals. Meshes
+/// without texture coordinates, or whose textures use a second set, take
+/// each texture's average value instead.
+pub fn load_gltf(path: &Path) -> Result<GltfScene, GltfError> {
+    let invalid = |message: String| GltfError::Invalid { path: path.to_path_buf(), message };
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let bytes = fs::read(path).map_err(|error| GltfError::Io { path: path.to_path_buf(), error })?;
+    let Gltf { document, mut blob } = Gltf::from_slice(&bytes).map_err(|error| invalid(error.to_string()))?;
+
+    let buffers = document.buffers()
+        .map(|buffer| {
+            let data = match buffer.source() {
+                ::gltf::buffer::Source::Bin => blob.take().ok_or_else(|| invalid("missing binary chunk".to_string()))?,
+                ::gltf::buffer::Source::Uri(uri) => read_uri(uri, directory).map_err(invalid)?,
+            };
+            if data.len() < buffer.length() {
+                return Err(invalid(format!("buffer {} is shorter than declared", buffer.index())));
+            }
+            Ok(data)
+        })
+        .collect::<Result<Vec<Vec<u8>>, GltfError>>()?;
+
+    let mut importer = Importer {
+        directory,
+        buffers,
+        textures: HashMap::new(),
+        scene: GltfScene { meshes: vec![], cameras: vec![] },
+    };
+
+    let scene = document.default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| invalid("the file contains no scene".to_string()))?;
+    for node in scene.nodes() {
+        importer.import_node(&node, &Matrix4::IDENTITY).map_err(invalid)?;
+    }
+
+    Ok(importer.scene)
+}
+
+struct Importer<'d> {
+    directory: &'d Path,
+    buffers: Vec<Vec<u8>>,
+    // Keyed by texture index and whether the image is sRGB encoded.
+    textures: HashMap<(usize, bool), ImportedTexture>,
+    scene: GltfScene,
+}
+
+impl Importer<'_> {
+    fn import_node(&mut self, node: &Node, parent: &Matrix4) -> Result<(), String> {
+        let transform = parent * &Matrix4::from_columns(node.transform().matrix());
+
+        if let Some(camera) = node.camera() {
+            if let Projection::Perspective(perspective) = camera.projection() {
+                // Cameras look down their local -z axis, with +y up.
+                let look_from = transform.transform_point(&Point3::origin());
+                let look_at = transform.transform_point(&Point3::new(0.0, 0.0, -1.0));
+                let view_up = transform.transform_vector(&Vec3::new(0.0, 1.0, 0.0));
+                self.scene.cameras.push(CameraDescription {
+                    look_from: [look_from.x(), look_from.y(), look_from.z()],
+                    look_at: [look_at.x(), look_at.y(), look_at.z()],
+                    view_up: [view_up.x(), view_up.y(), view_up.z()],
+                    vertical_fov: perspective.yfov().to_degrees(),
+                    aspect_ratio: perspective.aspect_ratio().unwrap_or(DEFAULT_ASPECT_RATIO),
+                    aperture: 0.0,
+                    focus_distance: None,
+                });
+            }
+        }
+
+        if let Some(mesh) = node.mesh() {
+            let name = node.name().or(mesh.name()).unwrap_or("mesh").to_string();
+            for primitive in mesh.primitives() {
+                if let Some(mesh) = self.import_primitive(&primitive, &transform)? {
+                    self.scene.meshes.push(GltfMesh { name: name.clone(), mesh });
+                }
+            }
+        }
+
+        for child in node.children() {
+            self.import_node(&child, &transform)?;
+        }
+        Ok(())
+    }
+
+    // Points and lines have no surface and are skipped.
+    fn import_primitive(&mut self, primitive: &::gltf::Primitive, transform: &Matrix4) -> Result<Option<TriangleMesh<'static>>, String> {
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+
+        let positions: Vec<Point3> = match reader.read_positions() {
+            Some(positions) => positions.map(|p| transform.transform_point(&Point3::new(p[0], p[1], p[2]))).collect(),
+            None => return Err("primitive has no positions".to_string()),
+        };
+
+        let normals: Vec<Vec3> = reader.read_normals()
+            .map(|normals| normals.map(|n| transform.transform_normal(&Vec3::new(n[0], n[1], n[2]))).collect())
+            .unwrap_or_default();
+        // glTF puts v = 0 at the top of an image, where textures expect it at
+        // the bottom.
+        let uvs: Vec<(f32, f32)> = reader.read_tex_coords(0)
+            .map(|uvs| uvs.into_f32().map(|uv| (uv[0], 1.0 - uv[1])).collect())
+            .unwrap_or_default();
+
+        let vertices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|index| index as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let mut triangles: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => vertices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+            // Every other triangle of a strip is wound the other way.
+            Mode::TriangleStrip => (0..vertices.len().saturating_sub(2))
+                .map(|i| if i % 2 == 0 {
+                    [vertices[i], vertices[i + 1], vertices[i + 2]]
+                } else {
+                    [vertices[i + 1], vertices[i], vertices[i + 2]]
+                })
+                .collect(),
+            Mode::TriangleFan => (1..vertices.len().saturating_sub(1))
+                .map(|i| [vertices[0], vertices[i], vertices[i + 1]])
+                .collect(),
+            _ => return Ok(None),
+        };
+
+        // A mirroring transform turns the winding inside out.
+        if transform.linear_determinant() < 0.0 {
+            for triangle in triangles.iter_mut() {
+                triangle.swap(1, 2);
+            }
+        }
+
+        let material = self.import_material(&primitive.material(), !uvs.is_empty())?;
+        TriangleMesh::new(positions, normals, uvs, triangles, material)
+            .map(Some)
+            .map_err(|error| error.to_string())
+    }
+
+    // The kind of material is chosen from the factors and average texture
+    // values; the textures themselves then vary its colours and roughness.
+    fn import_material(&mut self, material: &::gltf::Material, has_uvs: bool) -> Result<Box<dyn Material>, String> {
+        let pbr = material.pbr_metallic_roughness();
+
+        let [r, g, b, _] = pbr.base_color_factor();
+        let base_factor = Color::new(r, g, b);
+        let base_color: Arc<dyn Texture> = match pbr.base_color_texture() {
+            Some(info) => {
+                let texture = self.texture(&info.texture(), info.tex_coord(), has_uvs, true)?;
+                Arc::new(ScaledTexture::new(texture.texture, base_factor))
+            }
+            None => Arc::new(SolidColor::new(base_factor)),
+        };
+
+        let mut metallic = pbr.metallic_factor();
+        let roughness_factor = pbr.roughness_factor();
+        let roughness: Arc<dyn Texture> = match pbr.metallic_roughness_texture() {
+            // Roughness is stored in the green channel and metalness in blue.
+            Some(info) => {
+                let texture = self.texture(&info.texture(), info.tex_coord(), has_uvs, false)?;
+                metallic *= texture.average.z();
+                let roughness = Arc::new(ChannelTexture::new(texture.texture, 1));
+                Arc::new(ScaledTexture::new(roughness, Color::new(roughness_factor, roughness_factor, roughness_factor)))
+            }
+            None => Arc::new(SolidColor::scalar(roughness_factor)),
+        };
+
+        let [r, g, b] = material.emissive_factor();
+        let emission_factor = Color::new(r, g, b) * material.emissive_strength().unwrap_or(1.0);
+        let emission = match material.emissive_texture() {
+            Some(info) if !emission_factor.near_zero() => {
+                let texture = self.texture(&info.texture(), info.tex_coord(), has_uvs, true)?;
+                (!(texture.average.clone() * emission_factor.clone()).near_zero())
+                    .then(|| Arc::new(ScaledTexture::new(texture.texture, emission_factor)) as Arc<dyn Texture>)
+            }
+            _ => (!emission_factor.near_zero()).then(|| Arc::new(SolidColor::new(emission_factor)) as Arc<dyn Texture>),
+        };
+
+        let transmission = material.transmission().map_or(0.0, |transmission| transmission.transmission_factor());
+
+        let base: Box<dyn Material> = if let Some(emission) = emission {
+            Box::new(DiffuseLight::textured(emission))
+        } else if transmission >= 0.5 {
+            Box::new(Dielectric::new(material.ior().unwrap_or(1.5)))
+        } else if metallic >= 0.5 {
+            Box::new(Metal::textured(base_color, roughness))
+        } else {
+            Box::new(Lambertian::textured(base_color))
+        };
+
+        // A normal map is no use without the coordinates to look it up by.
+        Ok(match material.normal_texture() {
+            Some(info) if has_uvs && info.tex_coord() == 0 => {
+                let map = self.texture(&info.texture(), 0, has_uvs, false)?;
+                Box::new(NormalMapped::new(base, map.texture).with_strength(info.scale()))
+            }
+            _ => base,
+        })
+    }
+
+    // A texture that reads a set of surface coordinates other than the first,
+    // which is all the meshes keep, is reduced to its average.
+    fn texture(&mut self, texture: &::gltf::Texture, tex_coord: u32, has_uvs: bool, is_srgb: bool) -> Result<ImportedTexture, String> {
+        let imported = match self.textures.entry((texture.index(), is_srgb)) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                let image = load_image(self.directory, &self.buffers, &texture.source())?;
+                let image = if is_srgb {
+                    Image::new(image.width(), image.height(), image.pixels().iter().map(Color::srgb_to_linear).collect())
+                } else {
+                    image
+                };
+                let average = image.pixels().iter().fold(Color::origin(), |sum, pixel| sum + pixel.clone())
+                    / image.pixels().len() as f32;
+
+                // Both axes wrap the way the sampler says the first does.
+                let sampler = texture.sampler();
+                let wrap = match sampler.wrap_s() {
+                    WrappingMode::Repeat => WrapMode::Repeat,
+                    WrappingMode::MirroredRepeat => WrapMode::MirroredRepeat,
+                    WrappingMode::ClampToEdge => WrapMode::ClampToEdge,
+                };
+                let filter = match sampler.mag_filter() {
+                    Some(MagFilter::Nearest) => Filter::Nearest,
+                    _ => Filter::Bilinear,
+                };
+                let texture = Arc::new(ImageTexture::new(image).with_wrap(wrap).with_filter(filter));
+                entry.insert(ImportedTexture { texture, average }).clone()
+            }
+        };
+
+        Ok(if tex_coord == 0 && has_uvs {
+            imported
+        } else {
+            ImportedTexture { texture: Arc::new(SolidColor::new(imported.average.clone())), average: imported.average }
+        })
+    }
+}
+
+#[derive(Clone)]
+struct ImportedTexture {
+    texture: Arc<dyn Texture>,
+    average: Color,
+}
+
+fn load_image(directory: &Path, buffers: &[Vec<u8>], image: &::gltf::Image) -> Result<Image, String> {
+    let (bytes, mime_type) = match image.source() {
+        ::gltf::image::Source::View { view, mime_type } => {
+            let buffer = &buffers[view.buffer().index()];
+            let end = view.offset() + view.length();
+            if end > buffer.len() {
+                return Err(format!("image {} lies outside its buffer", image.index()));
+            }
+            (buffer[view.offset()..end].to_vec(), Some(mime_type.to_string()))
+        }
+        ::gltf::image::Source::Uri { uri, mime_type } => {
+            (read_uri(uri, directory)?, mime_type.map(|mime_type| mime_type.to_string()))
+        }
+    };
+
+    // Trust the data over the declared type.
+    let is_png = bytes.starts_with(b"\x89PNG");
+    let is_jpeg = bytes.starts_with(&[0xFF, 0xD8]);
+    let decoded = match (is_png, is_jpeg) {
+        (true, _) => read_png(&bytes[..]),
+        (_, true) => read_jpeg(&bytes[..]),
+        _ => return Err(format!("image {} is not PNG or JPEG ({})", image.index(), mime_type.unwrap_or_default())),
+    };
+    decoded.map_err(|error| format!("cannot decode image {}: {}", image.index(), error))
+}
+
+/// Reads an embedded base64 data URI or a file relative to `directory`.
+fn read_uri(uri: &str, directory: &Path) -> Result<Vec<u8>, String> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (header, payload) = data.split_once(',').ok_or_else(|| "malformed data URI".to_string())?;
+        if !header.ends_with(";base64") {
+            return Err("only base64 data URIs are supported".to_string());
+        }
+        return decode_base64(payload).ok_or_else(|| "malformed base64 in data URI".to_string());
+    }
+
+    if uri.contains("://") {
+        return Err(format!("'{}' is not a local file", uri));
+    }
+
+    let path = directory.join(percent_decode(uri));
+    fs::read(&path).map_err(|error| format!("cannot read '{}': {}", path.display(), error))
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+
+    let mut decoded = Vec::with_capacity(text.len() * 3 / 4);
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        accumulator = (accumulator << 6) | value(c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((accumulator >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Point3, Vec3};
+    use crate::gltf::{load_gltf, GltfError, GltfMesh};
+    use crate::hittable::Hittable;
+    use crate::ray::Ray;
+    use crate::utils::fixture;
+
+    fn find<'m>(meshes: &'m [GltfMesh], name: &str) -> &'m GltfMesh {
+        meshes.iter()
+            .find(|mesh| mesh.name == name)
+            .unwrap_or_else(|| panic!("no mesh {}", name))
+    }
+
+    #[test]
+    fn node_transforms_are_applied_down_the_hierarchy() {
+        let scene = load_gltf(&fixture("panels.gltf")).unwrap();
+        assert_eq!(scene.meshes.len(), 2);
+
+        let panel = &find(&scene.meshes, "panel").mesh;
+        assert_eq!(panel.triangle_count(), 2);
+        let rec = panel.hit(&Ray::new(Point3::new(1.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+        assert!(rec.is_hit && rec.front_face);
+        assert!((rec.t - 7.0).abs() < 1e-5);
+        assert!((&rec.normal - &Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+
+        // Mirroring must not turn the panel's front face away.
+        let mirrored = &find(&scene.meshes, "mirrored_panel").mesh;
+        let rec = mirrored.hit(&Ray::new(Point3::new(-3.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+        assert!(rec.is_hit && rec.front_face);
+        assert!((&rec.normal - &Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        assert!(!mirrored.hit(&Ray::new(Point3::new(-2.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY).is_hit);
+    }
+
+    #[test]
+    fn cameras_look_down_their_local_negative_z() {
+        let scene = load_gltf(&fixture("panels.gltf")).unwrap();
+        assert_eq!(scene.cameras.len(), 1);
+
+        let camera = &scene.cameras[0];
+        assert_eq!(camera.look_from, [0.0, 1.0, 5.0]);
+        assert_eq!(camera.look_at, [0.0, 1.0, 4.0]);
+        assert_eq!(camera.view_up, [0.0, 1.0, 0.0]);
+        assert!((camera.vertical_fov - 0.8f32.to_degrees()).abs() < 1e-4);
+        assert_eq!(camera.aspect_ratio, 2.0);
+    }
+
+    #[test]
+    fn binary_files_load_like_json_ones() {
+        // The same scene as panels.gltf, with its buffer in the binary chunk.
+        let json = load_gltf(&fixture("panels.gltf")).unwrap();
+        let binary = load_gltf(&fixture("panels.glb")).unwrap();
+
+        let names = |meshes: &[GltfMesh]| -> Vec<(String, usize)> {
+            meshes.iter().map(|mesh| (mesh.name.clone(), mesh.mesh.triangle_count())).collect()
+        };
+        assert_eq!(names(&binary.meshes), names(&json.meshes));
+
+        let ray = Ray::new(Point3::new(1.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = find(&binary.meshes, "panel").mesh.hit(&ray, 0.001, f32::INFINITY);
+        assert!(rec.is_hit && rec.front_face);
+        assert!((rec.t - 7.0).abs() < 1e-5);
+
+        assert_eq!(binary.cameras.len(), 1);
+        let (camera, expected) = (&binary.cameras[0], &json.cameras[0]);
+        assert_eq!(camera.look_from, expected.look_from);
+        assert_eq!(camera.look_at, expected.look_at);
+        assert_eq!(camera.view_up, expected.view_up);
+        assert_eq!(camera.vertical_fov, expected.vertical_fov);
+        assert_eq!(camera.aspect_ratio, expected.aspect_ratio);
+    }
+
+    #[test]
+    fn binary_files_without_their_binary_chunk_are_errors() {
+        match load_gltf(&fixture("panels_no_bin.glb")) {
+            Err(GltfError::Invalid { message, .. }) => assert_eq!(message, "missing binary chunk"),
+            Err(error) => panic!("expected an invalid file, got '{}'", error),
+            Ok(_) => panic!("expected an invalid file, got none"),
+        }
+    }
+
+    #[test]
+    fn base_color_textures_are_sampled_right_way_up() {
+        let scene = load_gltf(&fixture("textured.gltf")).unwrap();
+        let tile = &find(&scene.meshes, "tile").mesh;
+
+        // The image's top left texel is red and its bottom right one white.
+        let albedo_at = |x: f32, y: f32| {
+            let ray = Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+            let rec = tile.hit(&ray, 0.001, f32::INFINITY);
+            assert!(rec.is_hit);
+            rec.material.scatter(&ray, &rec).1
+        };
+        assert!((albedo_at(0.2, 0.8) - Color::new(1.0, 0.0, 0.0)).length() < 1e-5);
+        assert!((albedo_at(0.8, 0.2) - Color::new(1.0, 1.0, 1.0)).length() < 1e-5);
+    }
+}
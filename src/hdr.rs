@@ -0,0 +1,254 @@
+use std::io;
+use std::io::{BufRead, ErrorKind, Read};
+
+use crate::color::Color;
+use crate::image::Image;
+
+/// Reads a Radiance RGBE (.hdr) image, with or without run length encoding.
+pub fn read_hdr<R: BufRead>(input: &mut R) -> io::Result<Image> {
+    let magic = read_line(input)?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return Err(invalid_data("not a Radiance HDR file"));
+    }
+
+    // The header is a list of variables that ends with an empty line.
+    loop {
+        let line = read_line(input)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(&format!("unsupported pixel format '{}'", format)));
+            }
+        }
+    }
+
+    let (width, height, bottom_up) = parse_resolution(&read_line(input)?)?;
+
+    // Rows are added as they decode, never reserved from the header.
+    let mut rows = Vec::new();
+    for _ in 0..height {
+        rows.push(read_scanline(input, width)?);
+    }
+    if bottom_up {
+        rows.reverse();
+    }
+
+    let pixels = rows.into_iter().flatten().map(|rgbe| rgbe_to_color(&rgbe)).collect();
+    Ok(Image::new(width, height, pixels))
+}
+
+// Only the common orientations, with rows running along x, are supported.
+fn parse_resolution(line: &str) -> io::Result<(usize, usize, bool)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (y_sign, height, width) = match fields[..] {
+        [y, height, "+X", width] if y == "-Y" || y == "+Y" => (y, height, width),
+        _ => return Err(invalid_data(&format!("unsupported resolution line '{}'", line))),
+    };
+
+    match (width.parse::<usize>(), height.parse::<usize>()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height, y_sign == "+Y")),
+        _ => Err(invalid_data(&format!("invalid resolution line '{}'", line))),
+    }
+}
+
+fn read_scanline<R: Read>(input: &mut R, width: usize) -> io::Result<Vec<[u8; 4]>> {
+    let first = read_rgbe(input)?;
+
+    // Newer files mark run length encoded scanlines with two 2s and the width.
+    let is_rle = (8..0x8000).contains(&width)
+        && first[0] == 2
+        && first[1] == 2
+        && first[2] & 0x80 == 0;
+    if !is_rle {
+        return read_flat_scanline(input, width, first);
+    }
+    if ((first[2] as usize) << 8 | first[3] as usize) != width {
+        return Err(invalid_data("scanline width does not match the image width"));
+    }
+
+    // Each of the four components is encoded separately.
+    let mut scanline = vec![[0u8; 4]; width];
+    for component in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = read_byte(input)? as usize;
+            let (count, is_run) = if count > 128 { (count - 128, true) } else { (count, false) };
+            if count == 0 || x + count > width {
+                return Err(invalid_data("corrupt run length encoded scanline"));
+            }
+
+            if is_run {
+                let value = read_byte(input)?;
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[component] = value;
+                }
+            } else {
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[component] = read_byte(input)?;
+                }
+            }
+            x += count;
+        }
+    }
+
+    Ok(scanline)
+}
+
+// Uncompressed pixels, possibly using the original encoding where a pixel of
+// (1, 1, 1, n) repeats the previous pixel n times. Each further repeat pixel in
+// a row scales its count by another 256.
+fn read_flat_scanline<R: Read>(input: &mut R, width: usize, first: [u8; 4]) -> io::Result<Vec<[u8; 4]>> {
+    // Four repeat pixels in a row already count past 2^32.
+    const MAX_SHIFT: u32 = 24;
+
+    let mut scanline = Vec::new();
+    let mut shift = 0;
+    let mut next = Some(first);
+
+    while scanline.len() < width {
+        let pixel = match next.take() {
+            Some(pixel) => pixel,
+            None => read_rgbe(input)?,
+        };
+
+        if pixel[0] == 1 && pixel[1] == 1 && pixel[2] == 1 {
+            let previous = *scanline.last().ok_or_else(|| invalid_data("run without a pixel to repeat"))?;
+            if shift > MAX_SHIFT || pixel[3] == 0 {
+                return Err(invalid_data("corrupt run length encoded scanline"));
+            }
+            let count = (pixel[3] as usize) << shift;
+            if scanline.len() + count > width {
+                return Err(invalid_data("corrupt run length encoded scanline"));
+            }
+            scanline.extend(std::iter::repeat_n(previous, count));
+            shift += 8;
+        } else {
+            scanline.push(pixel);
+            shift = 0;
+        }
+    }
+
+    Ok(scanline)
+}
+
+fn rgbe_to_color(rgbe: &[u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::origin();
+    }
+
+    let scale = 2f32.powi(rgbe[3] as i32 - 136);
+    Color::new(
+        (rgbe[0] as f32 + 0.5) * scale,
+        (rgbe[1] as f32 + 0.5) * scale,
+        (rgbe[2] as f32 + 0.5) * scale,
+    )
+}
+
+fn read_rgbe<R: Read>(input: &mut R) -> io::Result<[u8; 4]> {
+    let mut rgbe = [0u8; 4];
+    input.read_exact(&mut rgbe)?;
+    Ok(rgbe)
+}
+
+fn read_byte<R: Read>(input: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8; 1];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_line<R: BufRead>(input: &mut R) -> io::Result<String> {
+    let mut line = Vec::new();
+    input.read_until(b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') {
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "unexpected end of header"));
+    }
+
+    String::from_utf8(line)
+        .map(|line| line.trim_end().to_string())
+        .map_err(|_| invalid_data("header is not valid text"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hdr::{read_hdr, rgbe_to_color};
+
+    fn file(resolution: &str, data: &[u8]) -> Vec<u8> {
+        let mut file = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", resolution).into_bytes();
+        file.extend_from_slice(data);
+        file
+    }
+
+    // The red channel of every pixel, row by row from the top.
+    fn reds(file: &[u8]) -> Vec<f32> {
+        let image = read_hdr(&mut &file[..]).unwrap();
+        image.pixels().iter().map(|pixel| pixel.x()).collect()
+    }
+
+    fn red(value: u8) -> f32 {
+        rgbe_to_color(&[value, 0, 0, 128]).x()
+    }
+
+    #[test]
+    fn flat_scanlines_decode_shared_exponents() {
+        let image = read_hdr(&mut &file("-Y 1 +X 2", &[128, 64, 32, 129, 0, 0, 0, 0])[..]).unwrap();
+        let pixel = image.pixel(0, 0);
+        assert_eq!((pixel.x(), pixel.y(), pixel.z()), (128.5 / 128.0, 64.5 / 128.0, 32.5 / 128.0));
+        assert_eq!(image.pixel(1, 0).length(), 0.0);
+    }
+
+    #[test]
+    fn orientation_decides_which_row_comes_first() {
+        let data = [10, 0, 0, 128, 20, 0, 0, 128];
+        assert_eq!(reds(&file("-Y 2 +X 1", &data)), [red(10), red(20)]);
+        assert_eq!(reds(&file("+Y 2 +X 1", &data)), [red(20), red(10)]);
+    }
+
+    #[test]
+    fn new_style_runs_encode_each_component_separately() {
+        let mut data = vec![2, 2, 0, 8];
+        // Red: a run of four, then four literal values.
+        data.extend_from_slice(&[128 + 4, 30, 4, 1, 2, 3, 4]);
+        // Green, blue and the exponent: runs across the scanline.
+        data.extend_from_slice(&[128 + 8, 0, 128 + 8, 0, 128 + 8, 128]);
+
+        let expected: Vec<f32> = [30, 30, 30, 30, 1, 2, 3, 4].into_iter().map(red).collect();
+        assert_eq!(reds(&file("-Y 1 +X 8", &data)), expected);
+    }
+
+    #[test]
+    fn old_style_runs_repeat_the_previous_pixel() {
+        // Two repeats, then one more scaled by 256, after a single pixel.
+        let data = [40, 0, 0, 128, 1, 1, 1, 2, 1, 1, 1, 1, 50, 0, 0, 128];
+        let reds = reds(&file("-Y 1 +X 260", &data));
+        assert_eq!(reds.len(), 260);
+        assert!(reds[..259].iter().all(|value| *value == red(40)));
+        assert_eq!(reds[259], red(50));
+    }
+
+    #[test]
+    fn bad_files_are_errors() {
+        // Truncated headers and pixel data.
+        assert!(read_hdr(&mut &b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n"[..]).is_err());
+        assert!(read_hdr(&mut &file("-Y 2 +X 2", &[1, 2, 3, 128])[..]).is_err());
+        // Sizes far beyond the data are not reserved up front.
+        assert!(read_hdr(&mut &file("-Y 4000000000 +X 4000000000", &[1, 2, 3, 128])[..]).is_err());
+        // Endless or empty runs of repeat pixels.
+        let mut data = vec![40, 0, 0, 128];
+        for _ in 0..10 {
+            data.extend_from_slice(&[1, 1, 1, 0]);
+        }
+        assert!(read_hdr(&mut &file("-Y 1 +X 100", &data)[..]).is_err());
+        let mut data = vec![40, 0, 0, 128];
+        for _ in 0..10 {
+            data.extend_from_slice(&[1, 1, 1, 1]);
+        }
+        assert!(read_hdr(&mut &file("-Y 1 +X 100", &data)[..]).is_err());
+        assert!(read_hdr(&mut &b"#?PFM\n\n-Y 1 +X 1\n"[..]).is_err());
+    }
+}
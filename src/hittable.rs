@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use crate::{Color, Point3, Ray, Vec3};
+use crate::aabb::Aabb;
+use crate::material::Material;
+
+#[derive(Clone)]
+pub struct HitRecord<'a> {
+    pub p: Point3,
+    pub normal: Vec3,
+    pub material: &'a dyn Material,
+    pub t: f32,
+    /// Surface coordinates of the hit, for texturing.
+    pub u: f32,
+    pub v: f32,
+    /// How the hit point moves as u and v increase, which with the normal
+    /// gives the tangent frame that normal and bump maps work in. Zero where
+    /// the surface has none, as at a sphere's poles.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    /// The colour interpolated from the vertices of a mesh that has them.
+    pub vertex_color: Option<Color>,
+    pub front_face: bool,
+    pub is_hit: bool,
+}
+
+impl HitRecord<'_> {
+    pub fn miss() -> HitRecord<'static> {
+        HitRecord {
+            p: Vec3::origin(),
+            normal: Vec3::origin(),
+            material: &Missed,
+            t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::origin(),
+            dpdv: Vec3::origin(),
+            vertex_color: None,
+            front_face: false,
+            is_hit: false,
+        }
+    }
+
+    pub fn get_face_normal(ray: &Ray, outward_normal: Vec3) -> (bool, Vec3) {
+        let front_face = ray.dir.dot(&outward_normal) < 0.0;
+        let normal = if front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+
+        (front_face, normal)
+    }
+}
+
+// Stands in for the material of a miss, which is never shaded.
+struct Missed;
+
+impl Material for Missed {
+    fn scatter(&self, ray_in: &Ray, _rec: &HitRecord) -> (Ray, Color, bool) {
+        (Ray::new(ray_in.orig.clone(), ray_in.dir.clone()), Color::origin(), false)
+    }
+}
+
+pub trait Hittable: Send + Sync {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_>;
+
+    fn bounding_box(&self) -> Aabb;
+
+    /// The solid angle density with which `random_direction` picks `direction`
+    /// from `origin`. Shapes that cannot be sampled as lights return zero.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f32 {
+        0.0
+    }
+
+    /// A random direction from `origin` towards the shape.
+    fn random_direction(&self, _origin: &Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+}
+
+
+// Boxed and shared objects are hittable too, so a `HittableList<Box<dyn Hittable>>`
+// can mix objects of different kinds.
+impl<H: Hittable + ?Sized> Hittable for Box<H> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f32 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random_direction(&self, origin: &Point3) -> Vec3 {
+        (**self).random_direction(origin)
+    }
+}
+
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f32 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random_direction(&self, origin: &Point3) -> Vec3 {
+        (**self).random_direction(origin)
+    }
+}
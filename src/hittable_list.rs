@@ -1,6 +1,10 @@
-use crate::{Ray, Vec3};
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
-use crate::material::DEFAULT_LAMBERTIAN;
+use crate::{Point3, Ray, Vec3};
+use crate::utils::random_float;
+
+/// A list of objects of any kind, such as spheres mixed with meshes.
+pub type World = HittableList<Box<dyn Hittable>>;
 
 pub struct HittableList<H: Hittable> {
     objects: Vec<H>,
@@ -11,23 +15,31 @@ impl<H: Hittable> HittableList<H> {
         HittableList { objects: vec![] }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, hittable: H) -> HittableList<H> {
         self.objects.push(hittable);
         self
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn into_objects(self) -> Vec<H> {
+        self.objects
+    }
+}
+
+impl<H: Hittable> Default for HittableList<H> {
+    fn default() -> Self {
+        HittableList::new()
+    }
 }
 
 impl<H: Hittable> Hittable for HittableList<H> {
-    fn hit<'a>(self: &'a Self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
         let mut closest_so_far = t_max;
-        let mut tmp_rec = HitRecord {
-            p: Vec3::origin(),
-            normal: Vec3::origin(),
-            material: &DEFAULT_LAMBERTIAN,
-            t: 0.0,
-            front_face: false,
-            is_hit: false,
-        };
+        let mut tmp_rec = HitRecord::miss();
 
         for object in self.objects.iter() {
             let rec = object.hit(ray, t_min, closest_so_far);
@@ -37,6 +49,33 @@ impl<H: Hittable> Hittable for HittableList<H> {
             }
         }
 
-        return tmp_rec;
+        tmp_rec
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter()
+            .fold(Aabb::empty(), |accum, object| Aabb::surrounding_box(&accum, &object.bounding_box()))
     }
-}
\ No newline at end of file
+
+    // Sampling picks one of the objects uniformly, so the density is the average
+    // of theirs.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f32 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f32 = self.objects.iter()
+            .map(|object| object.pdf_value(origin, direction))
+            .sum();
+        sum / self.objects.len() as f32
+    }
+
+    fn random_direction(&self, origin: &Point3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+
+        let index = ((random_float() * self.objects.len() as f32) as usize).min(self.objects.len() - 1);
+        self.objects[index].random_direction(origin)
+    }
+}
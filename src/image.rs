@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, ErrorKind};
+use std::path::Path;
+
+use crate::color::Color;
+use crate::hdr::read_hdr;
+use crate::jpeg::read_jpeg;
+use crate::pfm::read_pfm;
+use crate::png::read_png;
+
+/// An RGB image, stored row by row from the top. High dynamic range images hold
+/// linear values, while 8-bit ones are usually still sRGB encoded.
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Image {
+        assert_eq!(pixels.len(), width * height, "pixel count does not match the image size");
+        Image { width, height, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> &Color {
+        &self.pixels[y * self.width + x]
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+}
+
+/// Loads a PNG, JPEG, Radiance HDR or PFM image, chosen by the file extension.
+pub fn load_image(path: &Path) -> io::Result<Image> {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let mut input = BufReader::new(File::open(path)?);
+
+    match extension.as_deref() {
+        Some("png") => read_png(input),
+        Some("jpg") | Some("jpeg") => read_jpeg(input),
+        Some("hdr") => read_hdr(&mut input),
+        Some("pfm") => read_pfm(&mut input),
+        _ => Err(io::Error::new(ErrorKind::InvalidInput, "expected a .png, .jpg, .hdr or .pfm image")),
+    }
+}
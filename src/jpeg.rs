@@ -0,0 +1,46 @@
+use std::io;
+use std::io::{ErrorKind, Read};
+
+use jpeg_decoder::{Decoder, PixelFormat};
+
+use crate::color::Color;
+use crate::image::Image;
+
+/// Reads a baseline or progressive JPEG image. Values are scaled to [0, 1] and
+/// left sRGB encoded.
+pub fn read_jpeg<R: Read>(input: R) -> io::Result<Image> {
+    let mut decoder = Decoder::new(input);
+    let data = decoder.decode().map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?;
+    let info = decoder.info().ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "missing JPEG header"))?;
+
+    let pixels = match info.pixel_format {
+        PixelFormat::L8 => data.iter()
+            .map(|value| {
+                let value = *value as f32 / 255.0;
+                Color::new(value, value, value)
+            })
+            .collect(),
+        PixelFormat::L16 => data.chunks_exact(2)
+            .map(|bytes| {
+                // The decoder returns 16-bit samples in native byte order.
+                let value = u16::from_ne_bytes([bytes[0], bytes[1]]) as f32 / 65535.0;
+                Color::new(value, value, value)
+            })
+            .collect(),
+        PixelFormat::RGB24 => data.chunks_exact(3)
+            .map(|rgb| Color::new(rgb[0] as f32 / 255.0, rgb[1] as f32 / 255.0, rgb[2] as f32 / 255.0))
+            .collect(),
+        PixelFormat::CMYK32 => data.chunks_exact(4)
+            .map(|cmyk| {
+                let k = 1.0 - cmyk[3] as f32 / 255.0;
+                Color::new(
+                    (1.0 - cmyk[0] as f32 / 255.0) * k,
+                    (1.0 - cmyk[1] as f32 / 255.0) * k,
+                    (1.0 - cmyk[2] as f32 / 255.0) * k,
+                )
+            })
+            .collect(),
+    };
+
+    Ok(Image::new(info.width as usize, info.height as usize, pixels))
+}
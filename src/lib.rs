@@ -0,0 +1,49 @@
+pub use crate::color::Color;
+pub use crate::hittable::HitRecord;
+pub use crate::ray::Ray;
+pub use crate::vec3::Vec3;
+
+use crate::utils::random_float;
+
+pub mod vec3;
+pub mod ray;
+pub mod hittable;
+pub mod sphere;
+pub mod triangle;
+pub mod triangle_mesh;
+pub mod transform;
+pub mod obj;
+pub mod gltf;
+pub mod ply;
+pub mod stl;
+pub mod hittable_list;
+pub mod utils;
+pub mod camera;
+pub mod color;
+pub mod material;
+pub mod normal_map;
+pub mod texture;
+pub mod noise;
+pub mod procedural;
+pub mod aabb;
+pub mod bvh;
+pub mod background;
+pub mod image;
+pub mod hdr;
+pub mod jpeg;
+pub mod environment;
+pub mod sky;
+pub mod light;
+pub mod onb;
+pub mod film;
+pub mod ppm;
+pub mod png;
+pub mod exr;
+pub mod pfm;
+pub mod output;
+pub mod renderer;
+pub mod scenes;
+pub mod scene_file;
+pub mod scene_graph;
+
+pub type Point3 = Vec3;
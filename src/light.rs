@@ -0,0 +1,244 @@
+use std::f32::consts::PI;
+
+use crate::{Point3, Vec3};
+use crate::color::Color;
+use crate::hittable::Hittable;
+use crate::onb::Onb;
+use crate::utils::{clamp, degrees_to_radians};
+
+/// A light without geometry. Delta lights cannot be hit by rays, so they only
+/// contribute through shadow rays cast towards them.
+pub enum Light {
+    Point {
+        position: Point3,
+        intensity: Color,
+    },
+    /// A point light that only shines within a cone around `direction`, fading
+    /// out between `cos_falloff_start` and `cos_total_width`.
+    Spot {
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        cos_total_width: f32,
+        cos_falloff_start: f32,
+    },
+    /// Light arriving from infinitely far away and travelling along `direction`,
+    /// such as sunlight. A non-zero angular diameter gives soft shadows.
+    Directional {
+        direction: Vec3,
+        irradiance: Color,
+        cos_angular_radius: f32,
+    },
+}
+
+/// The light arriving at a point from a single light.
+pub struct LightSample {
+    /// Unit direction from the point towards the light.
+    pub direction: Vec3,
+    /// Distance to the light along `direction`, or infinity.
+    pub distance: f32,
+    /// The irradiance the light delivers, were it unoccluded.
+    pub irradiance: Color,
+}
+
+impl Light {
+    pub fn point(position: Point3, intensity: Color) -> Light {
+        Light::Point { position, intensity }
+    }
+
+    /// `cone_angle` is the full angle of the lit cone and `falloff_angle` the full
+    /// angle within which the light is at full strength, both in degrees.
+    pub fn spot(position: Point3, direction: Vec3, intensity: Color, cone_angle: f32, falloff_angle: f32) -> Light {
+        let cone_angle = clamp(cone_angle, 0.0, 180.0);
+        let falloff_angle = clamp(falloff_angle, 0.0, cone_angle);
+        Light::Spot {
+            position,
+            direction: direction.unit_vector(),
+            intensity,
+            cos_total_width: degrees_to_radians(cone_angle / 2.0).cos(),
+            cos_falloff_start: degrees_to_radians(falloff_angle / 2.0).cos(),
+        }
+    }
+
+    /// `angular_diameter` is in degrees; zero gives perfectly sharp shadows.
+    pub fn directional(direction: Vec3, irradiance: Color, angular_diameter: f32) -> Light {
+        Light::Directional {
+            direction: direction.unit_vector(),
+            irradiance,
+            cos_angular_radius: degrees_to_radians(clamp(angular_diameter, 0.0, 180.0) / 2.0).cos(),
+        }
+    }
+
+    /// The radiance seen looking along `direction` straight at the light. Only
+    /// directional lights with an angular size can be seen.
+    pub fn emitted(&self, direction: &Vec3) -> Color {
+        match self {
+            Light::Directional { direction: travel, irradiance, cos_angular_radius } if *cos_angular_radius < 1.0 => {
+                if -direction.unit_vector().dot(travel) >= *cos_angular_radius {
+                    irradiance / (2.0 * PI * (1.0 - cos_angular_radius))
+                } else {
+                    Color::origin()
+                }
+            }
+            _ => Color::origin(),
+        }
+    }
+
+    pub fn sample(&self, p: &Point3) -> LightSample {
+        match self {
+            Light::Point { position, intensity } => {
+                let to_light = position - p;
+                let distance = to_light.length();
+                LightSample {
+                    direction: to_light / distance,
+                    distance,
+                    irradiance: intensity / (distance * distance),
+                }
+            }
+            Light::Spot { position, direction, intensity, cos_total_width, cos_falloff_start } => {
+                let to_light = position - p;
+                let distance = to_light.length();
+                let to_light = to_light / distance;
+                let falloff = smoothstep(*cos_total_width, *cos_falloff_start, -to_light.dot(direction));
+                LightSample {
+                    direction: to_light,
+                    distance,
+                    irradiance: intensity * (falloff / (distance * distance)),
+                }
+            }
+            Light::Directional { direction, irradiance, cos_angular_radius } => {
+                let to_light = -direction;
+                let to_light = if *cos_angular_radius < 1.0 {
+                    Onb::build_from_w(&to_light).local(&Vec3::random_in_cone(*cos_angular_radius))
+                } else {
+                    to_light
+                };
+                LightSample {
+                    direction: to_light,
+                    distance: f32::INFINITY,
+                    irradiance: irradiance.clone(),
+                }
+            }
+        }
+    }
+}
+
+/// Everything that is sampled explicitly to light the scene.
+pub struct Lights<L: Hittable> {
+    /// Emissive shapes. They must also be part of the world to be visible.
+    pub shapes: L,
+    pub delta: Vec<Light>,
+}
+
+impl<L: Hittable> Lights<L> {
+    pub fn new(shapes: L) -> Lights<L> {
+        Lights { shapes, delta: vec![] }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, light: Light) -> Lights<L> {
+        self.delta.push(light);
+        self
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge0 == edge1 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::{Point3, Vec3};
+    use crate::color::Color;
+    use crate::light::Light;
+    use crate::utils::{degrees_to_radians, seed_rng};
+
+    #[test]
+    fn point_lights_fall_off_with_the_square_of_distance() {
+        let light = Light::point(Point3::new(0.0, 4.0, 0.0), Color::new(8.0, 8.0, 8.0));
+
+        let near = light.sample(&Point3::new(0.0, 2.0, 0.0));
+        let far = light.sample(&Point3::new(0.0, 0.0, 0.0));
+        assert!((near.distance - 2.0).abs() < 1e-5 && (far.distance - 4.0).abs() < 1e-5);
+        assert!((near.irradiance.x() - 2.0).abs() < 1e-5);
+        assert!((far.irradiance.x() - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn spot_lights_fade_smoothly_between_their_angles() {
+        // Lit fully within 20 degrees of straight down, and not at all past 40.
+        let light = Light::spot(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0), 80.0, 40.0);
+        let at_angle = |degrees: f32| {
+            let radians = degrees_to_radians(degrees);
+            let p = Point3::new(radians.sin(), 1.0 - radians.cos(), 0.0);
+            light.sample(&p).irradiance.x()
+        };
+
+        assert!((at_angle(0.0) - 1.0).abs() < 1e-4);
+        assert!((at_angle(19.0) - 1.0).abs() < 1e-4);
+        assert_eq!(at_angle(41.0), 0.0);
+        assert_eq!(at_angle(90.0), 0.0);
+
+        let mut previous = 1.0;
+        for degrees in 21..40 {
+            let irradiance = at_angle(degrees as f32);
+            assert!(irradiance > 0.0 && irradiance < 1.0 && irradiance < previous);
+            previous = irradiance;
+        }
+    }
+
+    #[test]
+    fn directional_lights_sample_within_their_cone() {
+        seed_rng(13);
+        let angular_diameter: f32 = 10.0;
+        let direction = Vec3::new(1.0, -2.0, 0.5);
+        let light = Light::directional(direction.clone(), Color::new(3.0, 3.0, 3.0), angular_diameter);
+        let cos_angular_radius = degrees_to_radians(angular_diameter / 2.0).cos();
+
+        for _ in 0..1000 {
+            let sample = light.sample(&Point3::origin());
+            assert!((sample.direction.length() - 1.0).abs() < 1e-4);
+            assert!(-sample.direction.dot(&direction.unit_vector()) >= cos_angular_radius - 1e-5);
+            assert_eq!(sample.distance, f32::INFINITY);
+            assert!((sample.irradiance.x() - 3.0).abs() < 1e-6);
+        }
+
+        let sharp = Light::directional(direction.clone(), Color::new(3.0, 3.0, 3.0), 0.0);
+        assert!((&sharp.sample(&Point3::origin()).direction + &direction.unit_vector()).length() < 1e-6);
+    }
+
+    // The renderer adds each delta light's irradiance once, unweighted, along a
+    // shadow ray that stops short of `distance`; a visible directional light's
+    // radiance has to spread that same irradiance over its cone.
+    #[test]
+    fn samples_give_the_renderer_the_whole_contribution() {
+        let p = Point3::new(1.0, 2.0, 3.0);
+        let position = Point3::new(-1.0, 4.0, 2.0);
+        for light in [
+            Light::point(position.clone(), Color::new(1.0, 1.0, 1.0)),
+            Light::spot(position.clone(), &p - &position, Color::new(1.0, 1.0, 1.0), 60.0, 30.0),
+        ] {
+            let sample = light.sample(&p);
+            assert!((sample.direction.length() - 1.0).abs() < 1e-5);
+            assert!((&(&p + &(&sample.direction * sample.distance)) - &position).length() < 1e-4);
+            assert!((sample.irradiance.x() - 1.0 / 9.0).abs() < 1e-5);
+            // Delta lights cannot be seen directly, so they are never counted twice.
+            assert!(light.emitted(&-sample.direction).near_zero());
+        }
+
+        let angular_diameter: f32 = 20.0;
+        let light = Light::directional(Vec3::new(0.0, 0.0, -1.0), Color::new(2.0, 2.0, 2.0), angular_diameter);
+        let cos_angular_radius = degrees_to_radians(angular_diameter / 2.0).cos();
+        let solid_angle = 2.0 * PI * (1.0 - cos_angular_radius);
+        // Looking back against the light's travel.
+        let radiance = light.emitted(&Vec3::new(0.0, 0.0, 1.0));
+        assert!((radiance.x() * solid_angle - 2.0).abs() < 1e-3);
+        assert!(light.emitted(&Vec3::new(0.0, 1.0, 1.0)).near_zero());
+    }
+}
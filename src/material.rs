@@ -1,57 +1,111 @@
 use std::borrow::Borrow;
+use std::f32::consts::PI;
 use std::ops::Neg;
+use std::sync::Arc;
 
 use crate::{Color, HitRecord, random_float, Ray, Vec3};
+use crate::texture::{SolidColor, Texture};
 
-pub trait Material {
+pub trait Material: Send + Sync {
     fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool);
+
+    fn emitted(&self, _rec: &HitRecord) -> Color {
+        Color::origin()
+    }
+
+    /// Whether `emitted` can be non-black, so objects of this material are
+    /// worth sampling as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// The BSDF times the cosine term for light arriving from `direction` and
+    /// leaving along `ray_in` reversed. Materials that scatter into a delta
+    /// distribution cannot be evaluated and return black.
+    fn eval(&self, _ray_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> Color {
+        Color::origin()
+    }
+
+    /// The solid angle density with which `scatter` picks `direction`. A zero
+    /// density marks the material as specular, so it is never light sampled.
+    fn pdf(&self, _ray_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> f32 {
+        0.0
+    }
+
+    /// Bends the normal of a hit away from the true surface's, as normal and
+    /// bump maps do. The renderer calls this once for each hit, before the
+    /// material is scattered or evaluated.
+    fn perturb_normal(&self, _rec: &mut HitRecord) {}
 }
 
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
-    pub const fn new(color: Color) -> Lambertian {
-        Lambertian { albedo: color }
+    pub fn new(color: Color) -> Lambertian {
+        Lambertian::textured(Arc::new(SolidColor::new(color)))
     }
-}
 
-pub const DEFAULT_LAMBERTIAN: Lambertian = Lambertian::new(Color::new(0.0, 0.0, 0.0));
+    pub fn textured(albedo: Arc<dyn Texture>) -> Lambertian {
+        Lambertian { albedo }
+    }
+}
 
 impl Material for Lambertian {
     fn scatter(&self, _ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
         let scatter_direction = get_scatter_direction(rec);
-        (Ray::new(rec.p.clone(), scatter_direction), self.albedo.clone(), true)
+        (Ray::new(rec.p.clone(), scatter_direction), self.albedo.value(rec.u, rec.v, &rec.p), true)
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p) * self.pdf(ray_in, rec, direction)
+    }
+
+    // Scattering around the normal by a random unit vector is cosine weighted.
+    fn pdf(&self, _ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        let cosine = rec.normal.dot(&direction.unit_vector());
+        cosine.max(0.0) / PI
     }
 }
 
 pub struct Metal {
-    albedo: Color,
-    fuzz: f32,
+    albedo: Arc<dyn Texture>,
+    fuzz: Arc<dyn Texture>,
 }
 
 impl Metal {
     pub fn new(color: Color, f: f32) -> Metal {
-        Metal { albedo: color, fuzz: if f < 1.0 { f } else { 1.0 } }
+        Metal::textured(Arc::new(SolidColor::new(color)), Arc::new(SolidColor::scalar(f)))
+    }
+
+    /// Fuzz is read from the red channel and, as with `new`, capped at 1.
+    pub fn textured(albedo: Arc<dyn Texture>, fuzz: Arc<dyn Texture>) -> Metal {
+        Metal { albedo, fuzz }
     }
 }
 
 impl Material for Metal {
     fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
+        let fuzz = self.fuzz.value(rec.u, rec.v, &rec.p).x().min(1.0);
         let reflected = ray_in.dir.unit_vector().reflect(&rec.normal);
-        let scattered = Ray::new(rec.p.clone(), reflected + Vec3::random_in_unit_sphere() * self.fuzz);
-        let is_scattered = (&scattered.dir).dot(&rec.normal) > 0.0;
-        (scattered, self.albedo.clone(), is_scattered)
+        let scattered = Ray::new(rec.p.clone(), reflected + Vec3::random_in_unit_sphere() * fuzz);
+        let is_scattered = scattered.dir.dot(&rec.normal) > 0.0;
+        (scattered, self.albedo.value(rec.u, rec.v, &rec.p), is_scattered)
     }
 }
 
 pub struct Dielectric {
-    index_of_refraction: f32,
+    index_of_refraction: Arc<dyn Texture>,
 }
 
 impl Dielectric {
     pub fn new(index_of_refraction: f32) -> Dielectric {
+        Dielectric::textured(Arc::new(SolidColor::scalar(index_of_refraction)))
+    }
+
+    /// The index of refraction is read from the red channel.
+    pub fn textured(index_of_refraction: Arc<dyn Texture>) -> Dielectric {
         Dielectric { index_of_refraction }
     }
 }
@@ -59,7 +113,8 @@ impl Dielectric {
 impl Material for Dielectric {
     fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
         let attenuation = Color::new(1.0, 1.0, 1.0);
-        let refraction_ratio = if rec.front_face { 1.0 / self.index_of_refraction } else { self.index_of_refraction };
+        let index_of_refraction = self.index_of_refraction.value(rec.u, rec.v, &rec.p).x();
+        let refraction_ratio = if rec.front_face { 1.0 / index_of_refraction } else { index_of_refraction };
 
         let direction = get_direction(ray_in, &rec.normal, refraction_ratio);
 
@@ -69,6 +124,143 @@ impl Material for Dielectric {
     }
 }
 
+pub struct DiffuseLight {
+    emit: Arc<dyn Texture>,
+    is_emissive: bool,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> DiffuseLight {
+        let is_emissive = !emit.near_zero();
+        DiffuseLight { emit: Arc::new(SolidColor::new(emit)), is_emissive }
+    }
+
+    /// A textured light is always treated as emissive, even if its texture
+    /// happens to be black everywhere.
+    pub fn textured(emit: Arc<dyn Texture>) -> DiffuseLight {
+        DiffuseLight { emit, is_emissive: true }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
+        (Ray::new(rec.p.clone(), ray_in.dir.clone()), Color::origin(), false)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.is_emissive
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        // Lights only emit from their front side.
+        if rec.front_face {
+            self.emit.value(rec.u, rec.v, &rec.p)
+        } else {
+            Color::origin()
+        }
+    }
+}
+
+/// Tints another material's albedo by the colour interpolated from a mesh's
+/// vertices, leaving hits without one as they are. A white material shows the
+/// vertex colours as they are.
+pub struct VertexColored<M> {
+    material: M,
+}
+
+impl<M: Material> VertexColored<M> {
+    pub fn new(material: M) -> VertexColored<M> {
+        VertexColored { material }
+    }
+}
+
+impl<M: Material> Material for VertexColored<M> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
+        let (scattered, attenuation, is_scattered) = self.material.scatter(ray_in, rec);
+        (scattered, tint(attenuation, rec), is_scattered)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.material.emitted(rec)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        tint(self.material.eval(ray_in, rec, direction), rec)
+    }
+
+    fn pdf(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        self.material.pdf(ray_in, rec, direction)
+    }
+
+    fn perturb_normal(&self, rec: &mut HitRecord) {
+        self.material.perturb_normal(rec)
+    }
+}
+
+fn tint(color: Color, rec: &HitRecord) -> Color {
+    match &rec.vertex_color {
+        Some(vertex_color) => color * vertex_color.clone(),
+        None => color,
+    }
+}
+
+impl<M: Material + ?Sized> Material for Box<M> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
+        (**self).scatter(ray_in, rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        (**self).emitted(rec)
+    }
+
+    fn is_emissive(&self) -> bool {
+        (**self).is_emissive()
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        (**self).eval(ray_in, rec, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        (**self).pdf(ray_in, rec, direction)
+    }
+
+    fn perturb_normal(&self, rec: &mut HitRecord) {
+        (**self).perturb_normal(rec)
+    }
+}
+
+// A shared material, such as one inherited by many objects of a scene graph.
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
+        (**self).scatter(ray_in, rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        (**self).emitted(rec)
+    }
+
+    fn is_emissive(&self) -> bool {
+        (**self).is_emissive()
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        (**self).eval(ray_in, rec, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        (**self).pdf(ray_in, rec, direction)
+    }
+
+    fn perturb_normal(&self, rec: &mut HitRecord) {
+        (**self).perturb_normal(rec)
+    }
+}
+
 fn get_direction(ray_in: &Ray, normal: &Vec3, refraction_ratio: f32) -> Vec3 {
     let unit_direction = ray_in.dir.unit_vector();
     let cos_theta = unit_direction.borrow().neg().dot(normal).min(1.0);
@@ -0,0 +1,329 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::{Point3, Vec3};
+
+/// A smooth pseudo-random function of space, giving values in roughly
+/// [-1, 1]. Generators built from the same seed give the same values, whatever
+/// the renderer's own random state.
+pub trait Noise: Send + Sync {
+    fn noise(&self, p: &Point3) -> f32;
+}
+
+/// Sums `octaves` layers of `noise`, each at twice the frequency and half the
+/// amplitude of the last, for detail at every scale.
+pub fn fbm<N: Noise + ?Sized>(noise: &N, p: &Point3, octaves: u32) -> f32 {
+    fractal_sum(noise, p, octaves, |value| value)
+}
+
+/// Like `fbm`, but sums the magnitude of each layer, giving the creases of
+/// billowing smoke or marble veins. The result is never negative.
+pub fn turbulence<N: Noise + ?Sized>(noise: &N, p: &Point3, octaves: u32) -> f32 {
+    fractal_sum(noise, p, octaves, f32::abs)
+}
+
+fn fractal_sum<N: Noise + ?Sized>(noise: &N, p: &Point3, octaves: u32, layer: impl Fn(f32) -> f32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut point = p.clone();
+    for _ in 0..octaves {
+        sum += amplitude * layer(noise.noise(&point));
+        amplitude *= 0.5;
+        point *= 2.0;
+    }
+    sum
+}
+
+// A shuffled table of 0 to 255, twice over so sums of two entries need no
+// wrapping. Hashes lattice points for all the generators.
+struct Permutation {
+    table: [u8; 512],
+}
+
+impl Permutation {
+    fn new(rng: &mut StdRng) -> Permutation {
+        let mut values: Vec<u8> = (0..=255).collect();
+        values.shuffle(rng);
+
+        let mut table = [0; 512];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = values[i % 256];
+        }
+        Permutation { table }
+    }
+
+    // Takes cell indices already reduced by `lattice`, plus at most one.
+    fn hash(&self, x: i32, y: i32, z: i32) -> usize {
+        let x = self.table[(x & 255) as usize] as usize;
+        let y = self.table[x + (y & 255) as usize] as usize;
+        self.table[y + (z & 255) as usize] as usize
+    }
+}
+
+// The index of the cell starting at `floor`, reduced to the table's period so
+// that stepping to a neighbour cannot overflow.
+fn lattice(floor: f32) -> i32 {
+    (floor as i64).rem_euclid(256) as i32
+}
+
+/// Ken Perlin's improved gradient noise.
+pub struct Perlin {
+    permutation: Permutation,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Perlin {
+        Perlin { permutation: Permutation::new(&mut StdRng::seed_from_u64(seed)) }
+    }
+}
+
+impl Noise for Perlin {
+    fn noise(&self, p: &Point3) -> f32 {
+        let (x0, y0, z0) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (x, y, z) = (p.x() - x0, p.y() - y0, p.z() - z0);
+        let (i, j, k) = (lattice(x0), lattice(y0), lattice(z0));
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let corner = |di: i32, dj: i32, dk: i32| {
+            let hash = self.permutation.hash(i + di, j + dj, k + dk);
+            perlin_gradient(hash, x - di as f32, y - dj as f32, z - dk as f32)
+        };
+
+        lerp(w,
+            lerp(v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0))),
+            lerp(v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1))))
+    }
+}
+
+// The quintic curve 6t^5 - 15t^4 + 10t^3, whose first and second derivatives
+// vanish at the lattice points.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+// The dot product with one of the twelve cube edge directions, picked by hash.
+fn perlin_gradient(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Simplex noise, which sums over the four corners of a tetrahedron rather
+/// than the eight of a cube, so is cheaper and has no grid-aligned artefacts.
+pub struct Simplex {
+    permutation: Permutation,
+}
+
+impl Simplex {
+    pub fn new(seed: u64) -> Simplex {
+        Simplex { permutation: Permutation::new(&mut StdRng::seed_from_u64(seed)) }
+    }
+}
+
+const SIMPLEX_GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+];
+
+impl Noise for Simplex {
+    fn noise(&self, p: &Point3) -> f32 {
+        const SKEW: f32 = 1.0 / 3.0;
+        const UNSKEW: f32 = 1.0 / 6.0;
+
+        // Find the skewed cell, then which of its six tetrahedra holds p.
+        let s = (p.x() + p.y() + p.z()) * SKEW;
+        let (i, j, k) = ((p.x() + s).floor(), (p.y() + s).floor(), (p.z() + s).floor());
+        let t = (i + j + k) * UNSKEW;
+        let x0 = [p.x() - (i - t), p.y() - (j - t), p.z() - (k - t)];
+
+        let (second, third) = if x0[0] >= x0[1] {
+            if x0[1] >= x0[2] {
+                ([1, 0, 0], [1, 1, 0])
+            } else if x0[0] >= x0[2] {
+                ([1, 0, 0], [1, 0, 1])
+            } else {
+                ([0, 0, 1], [1, 0, 1])
+            }
+        } else if x0[1] < x0[2] {
+            ([0, 0, 1], [0, 1, 1])
+        } else if x0[0] < x0[2] {
+            ([0, 1, 0], [0, 1, 1])
+        } else {
+            ([0, 1, 0], [1, 1, 0])
+        };
+
+        let (i, j, k) = (lattice(i), lattice(j), lattice(k));
+        [[0, 0, 0], second, third, [1, 1, 1]]
+            .iter()
+            .enumerate()
+            .map(|(n, offset)| {
+                let unskew = n as f32 * UNSKEW;
+                let d = [
+                    x0[0] - offset[0] as f32 + unskew,
+                    x0[1] - offset[1] as f32 + unskew,
+                    x0[2] - offset[2] as f32 + unskew,
+                ];
+                let falloff = 0.6 - d[0] * d[0] - d[1] * d[1] - d[2] * d[2];
+                if falloff <= 0.0 {
+                    return 0.0;
+                }
+                let hash = self.permutation.hash(i + offset[0], j + offset[1], k + offset[2]);
+                let gradient = SIMPLEX_GRADIENTS[hash % 12];
+                falloff.powi(4) * (gradient[0] * d[0] + gradient[1] * d[1] + gradient[2] * d[2])
+            })
+            .sum::<f32>()
+            * 32.0
+    }
+}
+
+/// Cellular noise: space is scattered with one random feature point per unit
+/// cell, and the distances to the nearest of them outline cells like cracked
+/// mud or crystals.
+pub struct Worley {
+    permutation: Permutation,
+    // Where each cell's feature point lies within it, picked by hash.
+    offsets: Vec<Vec3>,
+}
+
+/// What Worley noise finds around a point.
+pub struct WorleySample {
+    /// Distance to the nearest feature point.
+    pub f1: f32,
+    /// Distance to the second nearest, so `f2 - f1` is small near cell edges.
+    pub f2: f32,
+    /// A random value in [0, 1) shared by every point of the nearest cell.
+    pub cell_value: f32,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Worley {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let permutation = Permutation::new(&mut rng);
+        let offsets = (0..256).map(|_| Vec3::new(rng.gen(), rng.gen(), rng.gen())).collect();
+        Worley { permutation, offsets }
+    }
+
+    pub fn sample(&self, p: &Point3) -> WorleySample {
+        let (x0, y0, z0) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (i, j, k) = (lattice(x0), lattice(y0), lattice(z0));
+        let mut sample = WorleySample { f1: f32::INFINITY, f2: f32::INFINITY, cell_value: 0.0 };
+
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let hash = self.permutation.hash(i + di, j + dj, k + dk);
+                    let cell = Vec3::new(x0 + di as f32, y0 + dj as f32, z0 + dk as f32);
+                    let feature = cell + self.offsets[hash].clone();
+                    let distance = (&feature - p).length();
+                    if distance < sample.f1 {
+                        sample = WorleySample { f1: distance, f2: sample.f1, cell_value: hash as f32 / 256.0 };
+                    } else if distance < sample.f2 {
+                        sample.f2 = distance;
+                    }
+                }
+            }
+        }
+        sample
+    }
+}
+
+impl Noise for Worley {
+    /// The distance to the nearest feature point, mapped from [0, 1] onto
+    /// [-1, 1].
+    fn noise(&self, p: &Point3) -> f32 {
+        (2.0 * self.sample(p).f1 - 1.0).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Point3, Vec3};
+    use crate::noise::{fbm, turbulence, Noise, Perlin, Simplex, Worley};
+
+    fn points() -> impl Iterator<Item = Point3> {
+        (0..500).map(|i| {
+            let i = i as f32;
+            Point3::new(i * 0.173 - 40.0, i * 0.291 - 70.0, i * 0.057 + 3.0)
+        })
+    }
+
+    #[test]
+    fn noise_is_repeatable_and_depends_on_the_seed() {
+        let generators: [fn(u64) -> Box<dyn Noise>; 3] = [
+            |seed| Box::new(Perlin::new(seed)),
+            |seed| Box::new(Simplex::new(seed)),
+            |seed| Box::new(Worley::new(seed)),
+        ];
+
+        for generator in generators {
+            let (noise, same, other) = (generator(7), generator(7), generator(8));
+            assert!(points().all(|p| noise.noise(&p) == same.noise(&p)));
+            assert!(points().any(|p| noise.noise(&p) != other.noise(&p)));
+            assert!(points().all(|p| (-1.0..=1.0).contains(&noise.noise(&p))));
+        }
+    }
+
+    #[test]
+    fn gradient_noise_vanishes_on_the_lattice_and_varies_between() {
+        let perlin = Perlin::new(1);
+        assert_eq!(perlin.noise(&Point3::new(3.0, -2.0, 5.0)), 0.0);
+        assert!(points().any(|p| perlin.noise(&p).abs() > 0.2));
+
+        // Nearby points give nearby values.
+        let step = Vec3::new(1e-3, 0.0, 0.0);
+        assert!(points().all(|p| (perlin.noise(&p) - perlin.noise(&(&p + &step))).abs() < 1e-2));
+        let simplex = Simplex::new(1);
+        assert!(points().all(|p| (simplex.noise(&p) - simplex.noise(&(&p + &step))).abs() < 1e-2));
+    }
+
+    #[test]
+    fn fractal_sums_add_octaves() {
+        let perlin = Perlin::new(3);
+        let p = Point3::new(0.3, 0.7, 0.2);
+        assert_eq!(fbm(&perlin, &p, 1), perlin.noise(&p));
+        let expected = perlin.noise(&p) + 0.5 * perlin.noise(&(&p * 2.0));
+        assert!((fbm(&perlin, &p, 2) - expected).abs() < 1e-6);
+        assert!(points().all(|p| turbulence(&perlin, &p, 4) >= 0.0));
+    }
+
+    #[test]
+    fn far_points_and_many_octaves_stay_finite() {
+        let generators: [Box<dyn Noise>; 3] = [Box::new(Perlin::new(1)), Box::new(Simplex::new(1)), Box::new(Worley::new(1))];
+        let far = [Point3::new(3e9, -3e9, 0.5), Point3::new(f32::MAX, f32::MIN, 1e20), Point3::new(-2.5, 7.25, -1e12)];
+
+        for noise in generators.iter() {
+            for p in far.iter() {
+                assert!(noise.noise(p).is_finite());
+            }
+            assert!(fbm(noise.as_ref(), &Point3::new(0.3, 0.7, 0.2), 40).is_finite());
+            assert!(turbulence(noise.as_ref(), &Point3::new(-0.3, 0.7, 0.2), 40).is_finite());
+        }
+    }
+
+    #[test]
+    fn worley_distances_are_ordered() {
+        let worley = Worley::new(2);
+        for p in points() {
+            let sample = worley.sample(&p);
+            assert!(sample.f1 <= sample.f2);
+            assert!((0.0..1.0).contains(&sample.cell_value));
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use crate::{Color, HitRecord, Ray, Vec3};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::texture::Texture;
+
+// Surface coordinate steps over which bump maps are differentiated.
+const BUMP_DELTA: f32 = 5e-4;
+
+/// A material whose normals are read from a tangent-space normal map, in which
+/// red and green run along the u and v directions of the surface and blue away
+/// from it, each mapped from [0, 1] onto [-1, 1]. The map should hold linear
+/// values.
+pub struct NormalMapped<M> {
+    material: M,
+    map: Arc<dyn Texture>,
+    strength: f32,
+}
+
+impl<M: Material> NormalMapped<M> {
+    pub fn new(material: M, map: Arc<dyn Texture>) -> NormalMapped<M> {
+        NormalMapped { material, map, strength: 1.0 }
+    }
+
+    /// Scales how far the map tilts normals, flattening it below 1 and
+    /// exaggerating it above.
+    pub fn with_strength(mut self, strength: f32) -> NormalMapped<M> {
+        self.strength = strength;
+        self
+    }
+}
+
+impl<M: Material> Material for NormalMapped<M> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
+        self.material.scatter(ray_in, rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.material.emitted(rec)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        self.material.eval(ray_in, rec, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        self.material.pdf(ray_in, rec, direction)
+    }
+
+    fn perturb_normal(&self, rec: &mut HitRecord) {
+        self.material.perturb_normal(rec);
+
+        let encoded = self.map.value(rec.u, rec.v, &rec.p);
+        let local = Vec3::new(
+            (2.0 * encoded.x() - 1.0) * self.strength,
+            (2.0 * encoded.y() - 1.0) * self.strength,
+            2.0 * encoded.z() - 1.0,
+        );
+
+        let outward = outward_normal(rec);
+        let (tangent, bitangent) = tangent_frame(&outward, &rec.dpdu, &rec.dpdv);
+        let normal = &tangent * local.x() + &bitangent * local.y() + &outward * local.z();
+        set_outward_normal(rec, normal);
+    }
+}
+
+/// A material whose surface is raised by a height map, read from the red
+/// channel and multiplied by `scale` to give a distance. Only the slope of the
+/// height matters, as the surface itself does not move.
+pub struct BumpMapped<M> {
+    material: M,
+    height: Arc<dyn Texture>,
+    scale: f32,
+}
+
+impl<M: Material> BumpMapped<M> {
+    pub fn new(material: M, height: Arc<dyn Texture>, scale: f32) -> BumpMapped<M> {
+        BumpMapped { material, height, scale }
+    }
+}
+
+impl<M: Material> Material for BumpMapped<M> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> (Ray, Color, bool) {
+        self.material.scatter(ray_in, rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.material.emitted(rec)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        self.material.eval(ray_in, rec, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        self.material.pdf(ray_in, rec, direction)
+    }
+
+    // The bumped surface is p + height * n, whose tangents are the original
+    // ones tilted along the normal by the height's rate of change.
+    fn perturb_normal(&self, rec: &mut HitRecord) {
+        self.material.perturb_normal(rec);
+        if rec.dpdu.length_squared() < 1e-12 || rec.dpdv.length_squared() < 1e-12 {
+            return;
+        }
+
+        let height = |u: f32, v: f32, p: &Vec3| self.height.value(u, v, p).x() * self.scale;
+        let center = height(rec.u, rec.v, &rec.p);
+        let along_u = height(rec.u + BUMP_DELTA, rec.v, &(&rec.p + &(&rec.dpdu * BUMP_DELTA)));
+        let along_v = height(rec.u, rec.v + BUMP_DELTA, &(&rec.p + &(&rec.dpdv * BUMP_DELTA)));
+
+        let outward = outward_normal(rec);
+        let dpdu = &rec.dpdu + &(&outward * ((along_u - center) / BUMP_DELTA));
+        let dpdv = &rec.dpdv + &(&outward * ((along_v - center) / BUMP_DELTA));
+
+        // Surfaces whose uvs are mirrored wind the other way.
+        let normal = dpdu.cross(&dpdv);
+        let normal = if normal.dot(&outward) < 0.0 { -normal } else { normal };
+        set_outward_normal(rec, normal);
+    }
+}
+
+fn outward_normal(rec: &HitRecord) -> Vec3 {
+    if rec.front_face { rec.normal.clone() } else { -&rec.normal }
+}
+
+// Leaves the normal alone if the new one has no length.
+fn set_outward_normal(rec: &mut HitRecord, normal: Vec3) {
+    if normal.length_squared() < 1e-12 {
+        return;
+    }
+    let normal = normal.unit_vector();
+    rec.normal = if rec.front_face { normal } else { -normal };
+}
+
+// An orthonormal tangent and bitangent around `normal`, following the surface's
+// u and v directions where it has them.
+fn tangent_frame(normal: &Vec3, dpdu: &Vec3, dpdv: &Vec3) -> (Vec3, Vec3) {
+    let tangent = dpdu - &(normal * normal.dot(dpdu));
+    if tangent.length_squared() < 1e-12 {
+        let onb = Onb::build_from_w(normal);
+        return (onb.u().clone(), onb.v().clone());
+    }
+
+    let tangent = tangent.unit_vector();
+    let bitangent = normal.cross(&tangent);
+    if bitangent.dot(dpdv) < 0.0 { (tangent, -bitangent) } else { (tangent, bitangent) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Color, Point3, Ray, Vec3};
+    use crate::hittable::Hittable;
+    use crate::material::{Lambertian, Material};
+    use crate::normal_map::{BumpMapped, NormalMapped};
+    use crate::sphere::Sphere;
+    use crate::texture::{SolidColor, Texture};
+
+    // A height that rises along x.
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn value(&self, _u: f32, _v: f32, p: &Point3) -> Color {
+            Color::new(p.x(), p.x(), p.x())
+        }
+    }
+
+    fn shading_normal(material: impl Material + 'static, ray: &Ray) -> Vec3 {
+        let sphere = Sphere::new(Point3::origin(), 1.0, Box::new(material));
+        let mut rec = sphere.hit(ray, 0.001, f32::INFINITY);
+        assert!(rec.is_hit);
+        let material = rec.material;
+        material.perturb_normal(&mut rec);
+        rec.normal
+    }
+
+    #[test]
+    fn flat_normal_maps_leave_normals_alone_and_tilted_ones_follow_the_tangents() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let lambertian = || Lambertian::new(Color::new(0.5, 0.5, 0.5));
+
+        let flat = Arc::new(SolidColor::new(Color::new(0.5, 0.5, 1.0)));
+        let normal = shading_normal(NormalMapped::new(lambertian(), flat), &ray);
+        assert!((&normal - &Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+
+        // Leaning towards +u, which runs towards +x at the front of the sphere.
+        let tilted = Arc::new(SolidColor::new(Color::new(1.0, 0.5, 0.5)));
+        let normal = shading_normal(NormalMapped::new(lambertian(), tilted), &ray);
+        assert!((&normal - &Vec3::new(1.0, 0.0, 0.0)).length() < 1e-3);
+
+        // The same holds inside the sphere, where the normal faces inwards.
+        let tilted = Arc::new(SolidColor::new(Color::new(1.0, 0.5, 0.5)));
+        let inside = Ray::new(Point3::origin(), Vec3::new(0.0, 0.0, 1.0));
+        let normal = shading_normal(NormalMapped::new(lambertian(), tilted), &inside);
+        assert!((&normal - &Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn bump_maps_tilt_normals_down_the_slope() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let material = BumpMapped::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)), Arc::new(Ramp), 1.0);
+        let normal = shading_normal(material, &ray);
+
+        // A surface rising by one for each unit of x leans back at 45 degrees.
+        let expected = Vec3::new(-1.0, 0.0, 1.0).unit_vector();
+        assert!((&normal - &expected).length() < 1e-2);
+    }
+}
@@ -0,0 +1,626 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{Point3, Vec3};
+use crate::color::Color;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::texture::{ImageTexture, ScaledTexture, SolidColor, Texture, WrapMode};
+use crate::triangle_mesh::TriangleMesh;
+
+/// One mesh per group and material of a Wavefront OBJ file.
+pub struct ObjMesh {
+    /// The name given by the last `g` or `o` statement, or "default".
+    pub name: String,
+    /// The `usemtl` material, if any.
+    pub material_name: Option<String>,
+    pub mesh: TriangleMesh<'static>,
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "cannot read '{}': {}", path.display(), error),
+            ObjError::Parse { path, line, message } => {
+                write!(f, "{}, line {}: {}", path.display(), line, message)
+            }
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+/// Material parameters from an MTL file. They map onto the renderer's
+/// materials as follows:
+///
+/// - a non-black `Ke` emits light, as a `DiffuseLight`;
+/// - `illum` 4, 6 or 7, or a dissolve `d` below one, is glass with index `Ni`;
+/// - `illum` 3 is a mirror tinted by `Ks`, blurred more the lower `Ns` is;
+/// - anything else is a `Lambertian` of colour `Kd`.
+///
+/// `map_Kd` and `map_Ks` images are multiplied by `Kd` and `Ks`, unless those
+/// are black. Meshes without texture coordinates take the image's average.
+#[derive(Clone)]
+struct MtlMaterial {
+    diffuse: Color,
+    diffuse_map: Option<Arc<ImageTexture>>,
+    specular: Color,
+    specular_map: Option<Arc<ImageTexture>>,
+    emission: Color,
+    specular_exponent: f32,
+    index_of_refraction: f32,
+    dissolve: f32,
+    illumination: u32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            diffuse_map: None,
+            specular: Color::origin(),
+            specular_map: None,
+            emission: Color::origin(),
+            specular_exponent: 0.0,
+            index_of_refraction: 1.5,
+            dissolve: 1.0,
+            illumination: 2,
+        }
+    }
+}
+
+impl MtlMaterial {
+    fn build(&self, has_uvs: bool) -> Box<dyn Material> {
+        if !self.emission.near_zero() {
+            Box::new(DiffuseLight::new(self.emission.clone()))
+        } else if matches!(self.illumination, 4 | 6 | 7) || self.dissolve < 1.0 {
+            Box::new(Dielectric::new(self.index_of_refraction))
+        } else if self.illumination == 3 {
+            let albedo = if self.specular.near_zero() && self.specular_map.is_none() {
+                mapped_color(&self.diffuse, &self.diffuse_map, has_uvs)
+            } else {
+                mapped_color(&self.specular, &self.specular_map, has_uvs)
+            };
+            let fuzz = (2.0 / (self.specular_exponent.max(0.0) + 2.0)).sqrt();
+            Box::new(Metal::textured(albedo, Arc::new(SolidColor::scalar(fuzz))))
+        } else {
+            Box::new(Lambertian::textured(mapped_color(&self.diffuse, &self.diffuse_map, has_uvs)))
+        }
+    }
+}
+
+fn mapped_color(color: &Color, map: &Option<Arc<ImageTexture>>, has_uvs: bool) -> Arc<dyn Texture> {
+    let map = match map {
+        Some(map) => map,
+        None => return Arc::new(SolidColor::new(color.clone())),
+    };
+    let factor = if color.near_zero() { Color::new(1.0, 1.0, 1.0) } else { color.clone() };
+    if has_uvs {
+        Arc::new(ScaledTexture::new(Arc::clone(map) as Arc<dyn Texture>, factor))
+    } else {
+        Arc::new(SolidColor::new(map.average() * factor))
+    }
+}
+
+// A face corner's position, texture coordinate and normal indices.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct MeshBuilder {
+    name: String,
+    material_name: Option<String>,
+    triangles: Vec<[Corner; 3]>,
+}
+
+/// Loads a Wavefront OBJ file and the MTL libraries it refers to. Polygons are
+/// triangulated, and faces are split into one mesh per group and material.
+pub fn load_obj(path: &Path) -> Result<Vec<ObjMesh>, ObjError> {
+    let source = fs::read_to_string(path).map_err(|error| ObjError::Io { path: path.to_path_buf(), error })?;
+    parse_obj(&source, path)
+}
+
+/// Parses the contents of the OBJ file at `path`. MTL libraries are looked up
+/// next to it.
+pub fn parse_obj(source: &str, path: &Path) -> Result<Vec<ObjMesh>, ObjError> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let parse_error = |line: usize, message: String| ObjError::Parse { path: path.to_path_buf(), line, message };
+
+    let mut positions: Vec<Point3> = vec![];
+    let mut uvs: Vec<(f32, f32)> = vec![];
+    let mut normals: Vec<Vec3> = vec![];
+    let mut materials: HashMap<String, MtlMaterial> = HashMap::new();
+
+    let mut builders: Vec<MeshBuilder> = vec![];
+    let mut group = "default".to_string();
+    let mut material_name: Option<String> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut fields = line.split('#').next().unwrap_or("").split_whitespace();
+        let keyword = match fields.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = fields.collect();
+
+        match keyword {
+            "v" => {
+                // Some exporters append a vertex colour, which is ignored.
+                let values = parse_numbers::<f32>(&arguments, 3, 7).map_err(|message| parse_error(line_number, message))?;
+                positions.push(Point3::new(values[0], values[1], values[2]));
+            }
+            "vt" => {
+                let values = parse_numbers::<f32>(&arguments, 1, 3).map_err(|message| parse_error(line_number, message))?;
+                uvs.push((values[0], values.get(1).copied().unwrap_or(0.0)));
+            }
+            "vn" => {
+                let values = parse_numbers::<f32>(&arguments, 3, 3).map_err(|message| parse_error(line_number, message))?;
+                normals.push(Vec3::new(values[0], values[1], values[2]));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(parse_error(line_number, "a face needs at least three vertices".to_string()));
+                }
+                let corners = arguments.iter()
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<Corner>, String>>()
+                    .map_err(|message| parse_error(line_number, message))?;
+
+                let polygon: Vec<Point3> = corners.iter().map(|corner| positions[corner.0].clone()).collect();
+                let builder = current_builder(&mut builders, &group, &material_name);
+                for [a, b, c] in triangulate(&polygon) {
+                    builder.triangles.push([corners[a], corners[b], corners[c]]);
+                }
+            }
+            "g" | "o" => {
+                group = if arguments.is_empty() { "default".to_string() } else { arguments.join(" ") };
+            }
+            "usemtl" => {
+                let name = arguments.join(" ");
+                if !materials.contains_key(&name) {
+                    return Err(parse_error(line_number, format!("unknown material '{}'", name)));
+                }
+                material_name = Some(name);
+            }
+            "mtllib" => {
+                if arguments.is_empty() {
+                    return Err(parse_error(line_number, "mtllib needs a file name".to_string()));
+                }
+                for library in arguments.iter() {
+                    let library_path = directory.join(library);
+                    let library_source = fs::read_to_string(&library_path).map_err(|error| {
+                        parse_error(line_number, format!("cannot read material library '{}': {}", library, error))
+                    })?;
+                    materials.extend(parse_mtl(&library_source, &library_path)?);
+                }
+            }
+            // Smoothing groups, lines, points and free-form geometry are not
+            // needed for rendering triangles.
+            _ => {}
+        }
+    }
+
+    Ok(builders.into_iter()
+        .filter(|builder| !builder.triangles.is_empty())
+        .map(|builder| build_mesh(builder, &positions, &uvs, &normals, &materials))
+        .collect())
+}
+
+fn current_builder<'b>(builders: &'b mut Vec<MeshBuilder>, group: &str, material_name: &Option<String>) -> &'b mut MeshBuilder {
+    let position = builders.iter()
+        .position(|builder| builder.name == group && &builder.material_name == material_name);
+
+    match position {
+        Some(position) => &mut builders[position],
+        None => {
+            builders.push(MeshBuilder {
+                name: group.to_string(),
+                material_name: material_name.clone(),
+                triangles: vec![],
+            });
+            builders.last_mut().unwrap()
+        }
+    }
+}
+
+// Gives every distinct corner its own vertex. Normals and UVs are only kept if
+// every corner of the mesh has them.
+fn build_mesh(
+    builder: MeshBuilder,
+    positions: &[Point3],
+    uvs: &[(f32, f32)],
+    normals: &[Vec3],
+    materials: &HashMap<String, MtlMaterial>,
+) -> ObjMesh {
+    let corners = || builder.triangles.iter().flatten();
+    let has_uvs = corners().all(|corner| corner.1.is_some());
+    let has_normals = corners().all(|corner| corner.2.is_some());
+
+    let mut vertex_indices: HashMap<Corner, usize> = HashMap::new();
+    let mut mesh_positions = vec![];
+    let mut mesh_uvs = vec![];
+    let mut mesh_normals = vec![];
+    let mut indices = Vec::with_capacity(builder.triangles.len());
+
+    for triangle in builder.triangles.iter() {
+        let mut triangle_indices = [0; 3];
+        for (slot, corner) in triangle_indices.iter_mut().zip(triangle.iter()) {
+            let key = (corner.0, corner.1.filter(|_| has_uvs), corner.2.filter(|_| has_normals));
+            *slot = *vertex_indices.entry(key).or_insert_with(|| {
+                mesh_positions.push(positions[key.0].clone());
+                if let Some(uv) = key.1 {
+                    mesh_uvs.push(uvs[uv]);
+                }
+                if let Some(normal) = key.2 {
+                    mesh_normals.push(normals[normal].clone());
+                }
+                mesh_positions.len() - 1
+            });
+        }
+        indices.push(triangle_indices);
+    }
+
+    let material = builder.material_name.as_ref()
+        .and_then(|name| materials.get(name))
+        .cloned()
+        .unwrap_or_default();
+
+    ObjMesh {
+        name: builder.name,
+        material_name: builder.material_name,
+        // Every corner has a vertex, with a normal and UV when the mesh keeps them.
+        mesh: TriangleMesh::new(mesh_positions, mesh_normals, mesh_uvs, indices, material.build(has_uvs))
+            .expect("OBJ mesh arrays fit together"),
+    }
+}
+
+fn parse_mtl(source: &str, path: &Path) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let parse_error = |line: usize, message: String| ObjError::Parse { path: path.to_path_buf(), line, message };
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+    // Materials that share an image share its texture.
+    let mut maps: HashMap<(PathBuf, bool), Arc<ImageTexture>> = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut fields = line.split('#').next().unwrap_or("").split_whitespace();
+        let keyword = match fields.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = fields.collect();
+
+        if keyword == "newmtl" {
+            if arguments.is_empty() {
+                return Err(parse_error(line_number, "newmtl needs a name".to_string()));
+            }
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((arguments.join(" "), MtlMaterial::default()));
+            continue;
+        }
+
+        let is_known = matches!(keyword, "Kd" | "Ks" | "Ke" | "Ns" | "Ni" | "d" | "Tr" | "illum" | "map_Kd" | "map_Ks");
+        let material = match (&mut current, is_known) {
+            (Some((_, material)), _) => material,
+            (None, true) => return Err(parse_error(line_number, format!("'{}' before any newmtl", keyword))),
+            (None, false) => continue,
+        };
+
+        let number = |arguments: &[&str]| {
+            parse_numbers::<f32>(arguments, 1, 1).map(|values| values[0]).map_err(|message| parse_error(line_number, message))
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_color(&arguments).map_err(|message| parse_error(line_number, message))?,
+            "Ks" => material.specular = parse_color(&arguments).map_err(|message| parse_error(line_number, message))?,
+            "Ke" => material.emission = parse_color(&arguments).map_err(|message| parse_error(line_number, message))?,
+            "Ns" => material.specular_exponent = number(&arguments)?,
+            "Ni" => material.index_of_refraction = number(&arguments)?,
+            "d" => material.dissolve = number(&arguments)?,
+            "Tr" => material.dissolve = 1.0 - number(&arguments)?,
+            "illum" => {
+                material.illumination = parse_numbers::<u32>(&arguments, 1, 1)
+                    .map_err(|message| parse_error(line_number, message))?[0];
+            }
+            "map_Kd" | "map_Ks" => {
+                let map = load_map(&arguments, directory, &mut maps).map_err(|message| parse_error(line_number, message))?;
+                if keyword == "map_Kd" {
+                    material.diffuse_map = Some(map);
+                } else {
+                    material.specular_map = Some(map);
+                }
+            }
+            // Other maps and parameters have no counterpart.
+            _ => {}
+        }
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+    Ok(materials)
+}
+
+// Loads the image of a map statement. Options such as "-s 2 2 1" may come
+// before the file name; of them, only "-clamp on" is applied.
+fn load_map(
+    arguments: &[&str],
+    directory: &Path,
+    maps: &mut HashMap<(PathBuf, bool), Arc<ImageTexture>>,
+) -> Result<Arc<ImageTexture>, String> {
+    let mut clamp = false;
+    let mut rest = arguments;
+    while let Some(option) = rest.first().filter(|argument| argument.starts_with('-')) {
+        let value_count = match *option {
+            "-clamp" => {
+                clamp = rest.get(1) == Some(&"on");
+                1
+            }
+            "-blendu" | "-blendv" | "-bm" | "-boost" | "-cc" | "-imfchan" | "-texres" | "-type" => 1,
+            "-mm" => 2,
+            // One to three numbers, which may be negative.
+            "-o" | "-s" | "-t" => rest[1..].iter().take(3).take_while(|value| value.parse::<f32>().is_ok()).count().max(1),
+            _ => return Err(format!("unknown texture option '{}'", option)),
+        };
+        rest = rest.get(1 + value_count..).ok_or_else(|| format!("texture option '{}' needs a value", option))?;
+    }
+    if rest.is_empty() {
+        return Err("texture map needs a file name".to_string());
+    }
+
+    let file_name = rest.join(" ");
+    let key = (directory.join(&file_name), clamp);
+    if let Some(map) = maps.get(&key) {
+        return Ok(Arc::clone(map));
+    }
+    let texture = ImageTexture::load(&key.0).map_err(|error| format!("cannot read texture '{}': {}", file_name, error))?;
+    let wrap = if clamp { WrapMode::ClampToEdge } else { WrapMode::Repeat };
+    let map = Arc::new(texture.with_wrap(wrap));
+    maps.insert(key, Arc::clone(&map));
+    Ok(map)
+}
+
+fn parse_numbers<T: FromStr>(arguments: &[&str], min: usize, max: usize) -> Result<Vec<T>, String> {
+    if arguments.len() < min || arguments.len() > max {
+        return Err(if min == max {
+            format!("expected {} numbers, found {}", min, arguments.len())
+        } else {
+            format!("expected {} to {} numbers, found {}", min, max, arguments.len())
+        });
+    }
+
+    arguments.iter()
+        .map(|argument| argument.parse::<T>().map_err(|_| format!("invalid number '{}'", argument)))
+        .collect()
+}
+
+// Colours are given as RGB, or as a single grey value.
+fn parse_color(arguments: &[&str]) -> Result<Color, String> {
+    let values = parse_numbers::<f32>(arguments, 1, 3)?;
+    match values[..] {
+        [grey] => Ok(Color::new(grey, grey, grey)),
+        [r, g, b] => Ok(Color::new(r, g, b)),
+        _ => Err("expected one or three numbers".to_string()),
+    }
+}
+
+// Parses "v", "v/vt", "v//vn" or "v/vt/vn" into zero based indices. Negative
+// indices count back from the most recent element.
+fn parse_corner(corner: &str, position_count: usize, uv_count: usize, normal_count: usize) -> Result<Corner, String> {
+    let mut parts = corner.split('/');
+    let position = resolve_index(parts.next().unwrap_or(""), position_count, "vertex")?
+        .ok_or_else(|| format!("face vertex '{}' has no position", corner))?;
+    let uv = resolve_index(parts.next().unwrap_or(""), uv_count, "texture coordinate")?;
+    let normal = resolve_index(parts.next().unwrap_or(""), normal_count, "normal")?;
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex '{}'", corner));
+    }
+
+    Ok((position, uv, normal))
+}
+
+fn resolve_index(index: &str, count: usize, kind: &str) -> Result<Option<usize>, String> {
+    if index.is_empty() {
+        return Ok(None);
+    }
+
+    let value = index.parse::<i64>().map_err(|_| format!("invalid {} index '{}'", kind, index))?;
+    let resolved = if value < 0 { count as i64 + value } else { value - 1 };
+    if value == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} index {} is out of range", kind, index));
+    }
+    Ok(Some(resolved as usize))
+}
+
+/// Splits a planar polygon into triangles by ear clipping, keeping its winding.
+/// Polygons that cannot be clipped, such as self-intersecting ones, fall back
+/// to a fan.
+fn triangulate(polygon: &[Point3]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+    let fan = |remaining: &[usize]| -> Vec<[usize; 3]> {
+        (1..remaining.len() - 1).map(|i| [remaining[0], remaining[i], remaining[i + 1]]).collect()
+    };
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Newell's method gives the polygon's normal even when it is not convex.
+    let mut normal = Vec3::origin();
+    for i in 0..n {
+        let (a, b) = (&polygon[i], &polygon[(i + 1) % n]);
+        normal += Vec3::new(
+            (a.y() - b.y()) * (a.z() + b.z()),
+            (a.z() - b.z()) * (a.x() + b.x()),
+            (a.x() - b.x()) * (a.y() + b.y()),
+        );
+    }
+    if normal.length_squared() == 0.0 {
+        return fan(&(0..n).collect::<Vec<_>>());
+    }
+
+    // Project onto the plane the polygon is most nearly parallel to, keeping
+    // the orientation so that ears turn the same way as the whole polygon.
+    let axis = (0..3).max_by(|a, b| normal[*a].abs().total_cmp(&normal[*b].abs())).unwrap();
+    let (i, j) = if normal[axis] > 0.0 { ((axis + 1) % 3, (axis + 2) % 3) } else { ((axis + 2) % 3, (axis + 1) % 3) };
+    let points: Vec<(f32, f32)> = polygon.iter().map(|p| (p[i], p[j])).collect();
+
+    let cross = |o: usize, a: usize, b: usize| {
+        let (o, a, b) = (points[o], points[a], points[b]);
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&k| {
+            let (prev, current, next) = (remaining[(k + m - 1) % m], remaining[k], remaining[(k + 1) % m]);
+            if cross(prev, current, next) <= 0.0 {
+                return false;
+            }
+            remaining.iter()
+                .filter(|&&other| other != prev && other != current && other != next)
+                .all(|&other| {
+                    cross(prev, current, other) < 0.0 || cross(current, next, other) < 0.0 || cross(next, prev, other) < 0.0
+                })
+        });
+
+        match ear {
+            Some(k) => {
+                triangles.push([remaining[(k + m - 1) % m], remaining[k], remaining[(k + 1) % m]]);
+                remaining.remove(k);
+            }
+            None => {
+                triangles.extend(fan(&remaining));
+                return triangles;
+            }
+        }
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Point3, Vec3};
+    use crate::hittable::Hittable;
+    use crate::obj::{load_obj, ObjError, ObjMesh};
+    use crate::ray::Ray;
+    use crate::utils::fixture;
+
+    fn find<'m>(meshes: &'m [ObjMesh], name: &str, material_name: &str) -> &'m ObjMesh {
+        meshes.iter()
+            .find(|mesh| mesh.name == name && mesh.material_name.as_deref() == Some(material_name))
+            .unwrap_or_else(|| panic!("no mesh {} with material {}", name, material_name))
+    }
+
+    #[test]
+    fn cube_is_split_by_group_and_material() {
+        let meshes = load_obj(&fixture("cube.obj")).unwrap();
+
+        assert_eq!(meshes.len(), 3);
+        assert_eq!(find(&meshes, "sides", "red").mesh.triangle_count(), 8);
+        assert_eq!(find(&meshes, "caps", "lamp").mesh.triangle_count(), 2);
+        assert_eq!(find(&meshes, "caps", "glass").mesh.triangle_count(), 2);
+    }
+
+    #[test]
+    fn cube_faces_carry_normals_uvs_and_materials() {
+        let meshes = load_obj(&fixture("cube.obj")).unwrap();
+
+        let front = find(&meshes, "sides", "red").mesh
+            .hit(&Ray::new(Point3::new(0.25, -0.25, 2.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+        assert!(front.is_hit && front.front_face);
+        assert!((front.t - 1.5).abs() < 1e-5);
+        assert!((&front.normal - &Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        assert!((front.u - 0.75).abs() < 1e-5 && (front.v - 0.25).abs() < 1e-5);
+        assert!(front.material.emitted(&front).near_zero());
+
+        let top = find(&meshes, "caps", "lamp").mesh
+            .hit(&Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0)), 0.001, f32::INFINITY);
+        assert!(top.is_hit && top.front_face);
+        assert!(!top.material.emitted(&top).near_zero());
+    }
+
+    #[test]
+    fn concave_polygon_is_triangulated_inside_its_outline() {
+        let meshes = load_obj(&fixture("concave.obj")).unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].name, "letter_l");
+        assert_eq!(meshes[0].mesh.triangle_count(), 4);
+
+        let hits = |x: f32, y: f32| {
+            let rec = meshes[0].mesh.hit(&Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+            assert!(!rec.is_hit || rec.front_face);
+            rec.is_hit
+        };
+        for (x, y) in [(0.5, 0.5), (1.5, 0.5), (0.5, 1.5), (0.9, 0.9)] {
+            assert!(hits(x, y), "({}, {}) should be inside", x, y);
+        }
+        for (x, y) in [(1.5, 1.5), (1.1, 1.1), (2.5, 0.5)] {
+            assert!(!hits(x, y), "({}, {}) should be outside", x, y);
+        }
+    }
+
+    // quad.png is red and green along its top row, blue and white along its
+    // bottom one.
+    #[test]
+    fn material_maps_are_sampled_by_texture_coordinates() {
+        let meshes = load_obj(&fixture("textured.obj")).unwrap();
+        let albedo_at = |name: &str, material_name: &str, x: f32, y: f32| {
+            let ray = Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+            let rec = find(&meshes, name, material_name).mesh.hit(&ray, 0.001, f32::INFINITY);
+            assert!(rec.is_hit);
+            rec.material.scatter(&ray, &rec).1
+        };
+        let close = |a: Color, b: Color| (a - b).length() < 1e-4;
+
+        assert!(close(albedo_at("tile", "tiles", 0.1, 0.9), Color::new(1.0, 0.0, 0.0)));
+        assert!(close(albedo_at("tile", "tiles", 0.9, 0.1), Color::new(1.0, 1.0, 1.0)));
+        // The specular map is scaled by Ks.
+        assert!(close(albedo_at("mirror", "polished", 2.75, 0.75), Color::new(0.0, 0.5, 0.0)));
+        assert!(close(albedo_at("plain", "tiles", 4.5, 0.5), Color::new(0.5, 0.5, 0.5)));
+    }
+
+    fn parse_error(name: &str) -> (String, usize, String) {
+        match load_obj(&fixture(name)) {
+            Err(ObjError::Parse { path, line, message }) => {
+                (path.file_name().unwrap().to_string_lossy().into_owned(), line, message)
+            }
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("{} should not load", name),
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_line() {
+        let (file, line, message) = parse_error("bad_index.obj");
+        assert_eq!((file.as_str(), line), ("bad_index.obj", 5));
+        assert!(message.contains("out of range"), "{}", message);
+
+        let (file, line, _) = parse_error("bad_material.obj");
+        assert_eq!((file.as_str(), line), ("bad_material.mtl", 2));
+    }
+}
@@ -0,0 +1,70 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::exr::{ExrPixelType, write_exr};
+use crate::film::Film;
+use crate::pfm::write_pfm;
+use crate::png::{write_png16, write_png8};
+use crate::ppm::{write_p3, write_p5_luminance, write_p6};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    PpmAscii,
+    PpmBinary,
+    Pgm,
+    Png8,
+    Png16,
+    ExrHalf,
+    ExrFloat,
+    Pfm,
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<ImageFormat> {
+        match name {
+            "ppm" => Some(ImageFormat::PpmBinary),
+            "ppm-ascii" => Some(ImageFormat::PpmAscii),
+            "pgm" => Some(ImageFormat::Pgm),
+            "png" => Some(ImageFormat::Png8),
+            "png16" => Some(ImageFormat::Png16),
+            "exr" => Some(ImageFormat::ExrHalf),
+            "exr-float" => Some(ImageFormat::ExrFloat),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+
+    /// Picks the format matching the extension of `path`, if it is one we can write.
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::PpmBinary),
+            "pgm" => Some(ImageFormat::Pgm),
+            "png" => Some(ImageFormat::Png8),
+            "exr" => Some(ImageFormat::ExrHalf),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+}
+
+pub fn write_image<W: Write>(film: &Film, format: ImageFormat, out: W) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    match format {
+        ImageFormat::PpmAscii => write_p3(film, &mut out)?,
+        ImageFormat::PpmBinary => write_p6(film, &mut out)?,
+        ImageFormat::Pgm => write_p5_luminance(film, &mut out)?,
+        ImageFormat::Png8 => write_png8(film, &mut out)?,
+        ImageFormat::Png16 => write_png16(film, &mut out)?,
+        ImageFormat::ExrHalf => write_exr(film, ExrPixelType::Half, &mut out)?,
+        ImageFormat::ExrFloat => write_exr(film, ExrPixelType::Float, &mut out)?,
+        ImageFormat::Pfm => write_pfm(film, &mut out)?,
+    }
+    out.flush()
+}
+
+pub fn save_image(film: &Film, format: ImageFormat, path: &Path) -> io::Result<()> {
+    write_image(film, format, File::create(path)?)
+}
@@ -0,0 +1,131 @@
+use std::io;
+use std::io::{BufRead, ErrorKind, Write};
+
+use crate::color::Color;
+use crate::film::Film;
+use crate::image::Image;
+
+/// Writes the film's linear radiance as a little endian RGB Portable Float Map.
+pub fn write_pfm<W: Write>(film: &Film, out: &mut W) -> io::Result<()> {
+    writeln!(out, "PF")?;
+    writeln!(out, "{} {}", film.width(), film.height())?;
+    // A negative scale marks the data as little endian.
+    writeln!(out, "-1.0")?;
+
+    // PFM stores its scanlines from the bottom up.
+    for y in (0..film.height()).rev() {
+        for x in 0..film.width() {
+            let color = film.color(x, y);
+            out.write_all(&color.x().to_le_bytes())?;
+            out.write_all(&color.y().to_le_bytes())?;
+            out.write_all(&color.z().to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads an RGB ("PF") or greyscale ("Pf") Portable Float Map in either byte order.
+pub fn read_pfm<R: BufRead>(input: &mut R) -> io::Result<Image> {
+    let channels = match read_token(input)?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("not a Portable Float Map")),
+    };
+    let width = parse_token::<usize>(&read_token(input)?)?;
+    let height = parse_token::<usize>(&read_token(input)?)?;
+    let scale = parse_token::<f32>(&read_token(input)?)?;
+    if width == 0 || height == 0 || scale == 0.0 || !scale.is_finite() {
+        return Err(invalid_data("invalid Portable Float Map header"));
+    }
+    let little_endian = scale < 0.0;
+
+    // Not reserved up front, as the header's sizes may lie.
+    let mut rows = Vec::new();
+    for _ in 0..height {
+        let mut row = Vec::new();
+        for _ in 0..width {
+            let mut channel = [0.0; 3];
+            for value in channel.iter_mut().take(channels) {
+                let mut bytes = [0u8; 4];
+                input.read_exact(&mut bytes)?;
+                *value = if little_endian { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) };
+            }
+            row.push(if channels == 1 {
+                Color::new(channel[0], channel[0], channel[0])
+            } else {
+                Color::new(channel[0], channel[1], channel[2])
+            });
+        }
+        rows.push(row);
+    }
+    rows.reverse();
+
+    Ok(Image::new(width, height, rows.into_iter().flatten().collect()))
+}
+
+// Header fields are separated by whitespace, and a single whitespace character
+// separates the last of them from the pixel data.
+fn read_token<R: BufRead>(input: &mut R) -> io::Result<String> {
+    let mut token = String::new();
+    let mut byte = [0u8; 1];
+    loop {
+        input.read_exact(&mut byte)?;
+        if byte[0].is_ascii_whitespace() {
+            if token.is_empty() {
+                continue;
+            }
+            return Ok(token);
+        }
+        if token.len() >= 32 {
+            return Err(invalid_data("invalid Portable Float Map header"));
+        }
+        token.push(byte[0] as char);
+    }
+}
+
+fn parse_token<T: std::str::FromStr>(token: &str) -> io::Result<T> {
+    token.parse::<T>().map_err(|_| invalid_data(&format!("invalid header field '{}'", token)))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use crate::color::Color;
+    use crate::film::Film;
+    use crate::pfm::{read_pfm, write_pfm};
+
+    #[test]
+    fn written_maps_are_little_endian_and_bottom_up() {
+        let mut film = Film::new(2, 2);
+        film.add_sample(0, 0, Color::new(1.0, 2.0, 3.0));
+        film.add_sample(1, 1, Color::new(-4.0, 0.5, 8.0));
+
+        let mut data = vec![];
+        write_pfm(&film, &mut data).unwrap();
+        let header = b"PF\n2 2\n-1.0\n";
+        assert_eq!(&data[..header.len()], header);
+
+        let values: Vec<f32> = data[header.len()..]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        // The film's bottom row comes first.
+        assert_eq!(values, [0.0, 0.0, 0.0, -4.0, 0.5, 8.0, 1.0, 2.0, 3.0, 0.0, 0.0, 0.0]);
+
+        let image = read_pfm(&mut data.as_slice()).unwrap();
+        assert_eq!(image.pixel(0, 0).z(), 3.0);
+        assert_eq!(image.pixel(1, 1).x(), -4.0);
+    }
+
+    #[test]
+    fn oversized_headers_fail_without_reserving_memory() {
+        let mut input: &[u8] = b"PF\n4000000000 4000000000\n-1.0\n\0\0\0\0";
+        assert_eq!(read_pfm(&mut input).err().unwrap().kind(), ErrorKind::UnexpectedEof);
+    }
+}
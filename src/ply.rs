@@ -0,0 +1,440 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::SplitAsciiWhitespace;
+
+use crate::{Point3, Vec3};
+use crate::color::Color;
+use crate::material::{Material, VertexColored};
+use crate::triangle_mesh::TriangleMesh;
+
+/// The faces of a PLY file as a single mesh.
+pub struct PlyMesh {
+    pub mesh: TriangleMesh<'static>,
+    /// Linear per-vertex colours, or empty if the file has none.
+    pub vertex_colors: Vec<Color>,
+}
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl Display for PlyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io { path, error } => write!(f, "cannot read '{}': {}", path.display(), error),
+            PlyError::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl Error for PlyError {}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        Some(match name {
+            "char" | "int8" => ScalarType::Int8,
+            "uchar" | "uint8" => ScalarType::UInt8,
+            "short" | "int16" => ScalarType::Int16,
+            "ushort" | "uint16" => ScalarType::UInt16,
+            "int" | "int32" => ScalarType::Int32,
+            "uint" | "uint32" => ScalarType::UInt32,
+            "float" | "float32" => ScalarType::Float32,
+            "double" | "float64" => ScalarType::Float64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    // Integer colour channels span the type's range; float ones are in [0, 1].
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::UInt8 => 255.0,
+            ScalarType::UInt16 => 65535.0,
+            ScalarType::Int8 => 127.0,
+            ScalarType::Int16 => 32767.0,
+            ScalarType::Int32 => i32::MAX as f64,
+            ScalarType::UInt32 => u32::MAX as f64,
+            ScalarType::Float32 | ScalarType::Float64 => 1.0,
+        }
+    }
+}
+
+enum PropertyType {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+struct Property {
+    name: String,
+    kind: PropertyType,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property(&self, names: &[&str]) -> Option<usize> {
+        self.properties.iter().position(|property| names.contains(&property.name.as_str()))
+    }
+}
+
+// Reads the values following the header, one at a time.
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], little_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, kind: ScalarType) -> Result<f64, String> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().ok_or_else(|| "unexpected end of data".to_string())?;
+                token.parse().map_err(|_| format!("invalid number '{}'", token))
+            }
+            Body::Binary { data, little_endian } => {
+                let size = kind.size();
+                if data.len() < size {
+                    return Err("unexpected end of data".to_string());
+                }
+                let mut bytes = [0u8; 8];
+                bytes[..size].copy_from_slice(&data[..size]);
+                if !*little_endian {
+                    bytes[..size].reverse();
+                }
+                *data = &data[size..];
+
+                let [b0, b1, b2, b3, ..] = bytes;
+                Ok(match kind {
+                    ScalarType::Int8 => b0 as i8 as f64,
+                    ScalarType::UInt8 => b0 as f64,
+                    ScalarType::Int16 => i16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::UInt16 => u16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::Int32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::UInt32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::Float32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::Float64 => f64::from_le_bytes(bytes),
+                })
+            }
+        }
+    }
+
+    // Scalars are returned as one value, lists as their items.
+    fn read_property(&mut self, property: &Property) -> Result<Vec<f64>, String> {
+        match property.kind {
+            PropertyType::Scalar(kind) => Ok(vec![self.read(kind)?]),
+            PropertyType::List { count, item } => {
+                let count = self.read(count)?;
+                if count < 0.0 || count.fract() != 0.0 {
+                    return Err(format!("invalid list length {}", count));
+                }
+                (0..count as usize).map(|_| self.read(item)).collect()
+            }
+        }
+    }
+}
+
+/// Loads the `vertex` and `face` elements of an ASCII or binary PLY file into
+/// a mesh of the given material. Vertices may carry normals (`nx`, `ny`,
+/// `nz`), texture coordinates (`u`, `v` or `s`, `t`) and colours (`red`,
+/// `green`, `blue`), which are taken to be sRGB and tint the material's
+/// albedo, so a white material shows them as they are. Normals of zero length
+/// fall back to the face normal. Polygons are split into fans.
+pub fn load_ply(path: &Path, material: Box<dyn Material>) -> Result<PlyMesh, PlyError> {
+    let data = fs::read(path).map_err(|error| PlyError::Io { path: path.to_path_buf(), error })?;
+    parse_ply(&data, path, material)
+}
+
+/// Parses the contents of the PLY file at `path`.
+pub fn parse_ply(data: &[u8], path: &Path, material: Box<dyn Material>) -> Result<PlyMesh, PlyError> {
+    read_ply(data, material).map_err(|message| PlyError::Parse { path: path.to_path_buf(), message })
+}
+
+fn read_ply(data: &[u8], material: Box<dyn Material>) -> Result<PlyMesh, String> {
+    const END_HEADER: &[u8] = b"end_header";
+    let header_end = data.windows(END_HEADER.len())
+        .position(|window| window == END_HEADER)
+        .ok_or_else(|| "missing end_header".to_string())?;
+    // The body starts after the line ending of end_header.
+    let body_start = data[header_end..].iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(data.len(), |offset| header_end + offset + 1);
+
+    let header = std::str::from_utf8(&data[..header_end]).map_err(|_| "header is not text".to_string())?;
+    let (format, elements) = parse_header(header)?;
+
+    let mut body = match format {
+        Format::Ascii => {
+            let text = std::str::from_utf8(&data[body_start..]).map_err(|_| "ASCII data is not text".to_string())?;
+            Body::Ascii(text.split_ascii_whitespace())
+        }
+        _ => Body::Binary { data: &data[body_start..], little_endian: format == Format::BinaryLittleEndian },
+    };
+
+    let mut positions: Vec<Point3> = vec![];
+    let mut normals: Vec<Vec3> = vec![];
+    let mut uvs: Vec<(f32, f32)> = vec![];
+    let mut vertex_colors: Vec<Color> = vec![];
+    let mut triangles: Vec<[usize; 3]> = vec![];
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut body, element, &mut positions, &mut normals, &mut uvs, &mut vertex_colors)?,
+            "face" => read_faces(&mut body, element, &mut triangles)?,
+            // Other elements still have to be read past.
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        body.read_property(property)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if triangles.is_empty() {
+        return Err("the file contains no faces".to_string());
+    }
+
+    let material: Box<dyn Material> = if vertex_colors.is_empty() {
+        material
+    } else {
+        Box::new(VertexColored::new(material))
+    };
+    let mesh = TriangleMesh::colored(positions, normals, uvs, vertex_colors.clone(), triangles, material)
+        .map_err(|error| error.to_string())?;
+    Ok(PlyMesh { mesh, vertex_colors })
+}
+
+fn parse_header(header: &str) -> Result<(Format, Vec<Element>), String> {
+    let mut lines = header.lines().map(|line| line.trim());
+    if lines.next() != Some("ply") {
+        return Err("not a PLY file".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(format!("unknown format '{}'", name)),
+                });
+            }
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| format!("invalid count for element '{}'", name))?;
+                elements.push(Element { name: name.to_string(), count, properties: vec![] });
+            }
+            ["property", "list", count, item, name] => {
+                let kind = PropertyType::List { count: scalar_type(count)?, item: scalar_type(item)? };
+                add_property(&mut elements, name, kind)?;
+            }
+            ["property", kind, name] => add_property(&mut elements, name, PropertyType::Scalar(scalar_type(kind)?))?,
+            _ => return Err(format!("unexpected header line '{}'", line)),
+        }
+    }
+
+    Ok((format.ok_or_else(|| "missing format".to_string())?, elements))
+}
+
+fn scalar_type(name: &str) -> Result<ScalarType, String> {
+    ScalarType::parse(name).ok_or_else(|| format!("unknown property type '{}'", name))
+}
+
+fn add_property(elements: &mut [Element], name: &str, kind: PropertyType) -> Result<(), String> {
+    let element = elements.last_mut().ok_or_else(|| format!("property '{}' outside an element", name))?;
+    element.properties.push(Property { name: name.to_string(), kind });
+    Ok(())
+}
+
+fn read_vertices(
+    body: &mut Body,
+    element: &Element,
+    positions: &mut Vec<Point3>,
+    normals: &mut Vec<Vec3>,
+    uvs: &mut Vec<(f32, f32)>,
+    colors: &mut Vec<Color>,
+) -> Result<(), String> {
+    let all = |names: [&[&str]; 3]| -> Option<[usize; 3]> {
+        Some([element.property(names[0])?, element.property(names[1])?, element.property(names[2])?])
+    };
+    let position = all([&["x"], &["y"], &["z"]]).ok_or_else(|| "vertices have no position".to_string())?;
+    let normal = all([&["nx"], &["ny"], &["nz"]]);
+    let color = all([&["red", "r"], &["green", "g"], &["blue", "b"]]);
+    let uv = element.property(&["u", "s", "texture_u"]).zip(element.property(&["v", "t", "texture_v"]));
+    let color_scale = color.map_or(1.0, |[red, _, _]| match element.properties[red].kind {
+        PropertyType::Scalar(kind) => kind.color_scale(),
+        PropertyType::List { .. } => 1.0,
+    });
+
+    for _ in 0..element.count {
+        // A list property yields no single value and is read as zero.
+        let values: Vec<f64> = element.properties.iter()
+            .map(|property| body.read_property(property).map(|values| values.first().copied().unwrap_or(0.0)))
+            .collect::<Result<_, _>>()?;
+        let vector = |[x, y, z]: [usize; 3]| Vec3::new(values[x] as f32, values[y] as f32, values[z] as f32);
+
+        positions.push(vector(position));
+        if let Some(normal) = normal {
+            normals.push(vector(normal));
+        }
+        if let Some((u, v)) = uv {
+            uvs.push((values[u] as f32, values[v] as f32));
+        }
+        if let Some(color) = color {
+            colors.push((vector(color) / color_scale as f32).srgb_to_linear());
+        }
+    }
+    Ok(())
+}
+
+fn read_faces(body: &mut Body, element: &Element, triangles: &mut Vec<[usize; 3]>) -> Result<(), String> {
+    let indices = element.property(&["vertex_indices", "vertex_index"])
+        .ok_or_else(|| "faces have no vertex_indices".to_string())?;
+
+    for _ in 0..element.count {
+        for (i, property) in element.properties.iter().enumerate() {
+            let values = body.read_property(property)?;
+            if i != indices {
+                continue;
+            }
+            if values.iter().any(|value| *value < 0.0 || value.fract() != 0.0) {
+                return Err("invalid vertex index in face".to_string());
+            }
+            let corners: Vec<usize> = values.iter().map(|value| *value as usize).collect();
+            for k in 1..corners.len().saturating_sub(1) {
+                triangles.push([corners[0], corners[k], corners[k + 1]]);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{Point3, Vec3};
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ply::{load_ply, parse_ply, PlyMesh};
+    use crate::ray::Ray;
+    use crate::utils::fixture;
+
+    fn load(name: &str) -> PlyMesh {
+        load_with(name, Color::new(0.5, 0.5, 0.5))
+    }
+
+    fn load_with(name: &str, albedo: Color) -> PlyMesh {
+        load_ply(&fixture(name), Box::new(Lambertian::new(albedo))).unwrap()
+    }
+
+    #[test]
+    fn ascii_faces_are_triangulated_with_normals_and_colors() {
+        let ply = load("panels.ply");
+        assert_eq!(ply.mesh.triangle_count(), 3);
+
+        assert_eq!(ply.vertex_colors.len(), 5);
+        assert!((&ply.vertex_colors[0] - &Color::new(1.0, 0.0, 0.0)).length() < 1e-5);
+        assert!((ply.vertex_colors[4].x() - Color::new(0.5, 0.5, 0.5).srgb_to_linear().x()).abs() < 0.01);
+
+        for x in [0.5, 1.5] {
+            let rec = ply.mesh.hit(&Ray::new(Point3::new(x, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+            assert!(rec.is_hit && rec.front_face);
+            assert!((&rec.normal - &Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn binary_files_of_either_endianness_match_ascii() {
+        let ascii = load("panels.ply");
+        for name in ["panels_le.ply", "panels_be.ply"] {
+            let binary = load(name);
+            assert_eq!(binary.mesh.triangle_count(), ascii.mesh.triangle_count());
+            assert_eq!(binary.vertex_colors.len(), ascii.vertex_colors.len());
+            for (a, b) in ascii.vertex_colors.iter().zip(&binary.vertex_colors) {
+                assert!((a - b).length() < 1e-6);
+            }
+
+            let ray = Ray::new(Point3::new(1.2, 0.3, 2.0), Vec3::new(0.1, 0.0, -1.0));
+            let (a, b) = (ascii.mesh.hit(&ray, 0.001, f32::INFINITY), binary.mesh.hit(&ray, 0.001, f32::INFINITY));
+            assert!(a.is_hit && b.is_hit);
+            assert!((a.t - b.t).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn vertex_colors_tint_the_albedo() {
+        let ply = load_with("panels.ply", Color::new(1.0, 1.0, 1.0));
+        // Halfway between the red corner and the green-blue edge of the square.
+        let ray = Ray::new(Point3::new(0.5, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = ply.mesh.hit(&ray, 0.001, f32::INFINITY);
+        assert!(rec.is_hit);
+
+        let expected = Color::new(0.5, 0.25, 0.25);
+        assert!((rec.vertex_color.as_ref().unwrap() - &expected).length() < 1e-5);
+        let (_, attenuation, _) = rec.material.scatter(&ray, &rec);
+        assert!((&attenuation - &expected).length() < 1e-5);
+    }
+
+    #[test]
+    fn zero_normals_fall_back_to_the_face_normal() {
+        let data = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            property float nx\nproperty float ny\nproperty float nz\nelement face 1\n\
+            property list uchar int vertex_indices\nend_header\n\
+            0 0 0 0 0 0\n1 0 0 0 0 0\n0 1 0 0 0 0\n3 0 1 2\n";
+        let ply = parse_ply(data, &PathBuf::from("zero.ply"), Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))
+            .unwrap();
+
+        let rec = ply.mesh.hit(&Ray::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+        assert!(rec.is_hit && rec.vertex_color.is_none());
+        assert!((&rec.normal - &Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+    }
+}
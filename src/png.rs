@@ -0,0 +1,125 @@
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+
+use ::png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+use crate::color::Color;
+use crate::film::Film;
+use crate::image::Image;
+
+/// Writes the film as an 8-bit RGB PNG image.
+pub fn write_png8<W: Write>(film: &Film, out: W) -> io::Result<()> {
+    let data: Vec<u8> = film.pixels().iter()
+        .flat_map(|pixel| pixel.mean().to_rgb8())
+        .collect();
+
+    write_png(film, out, BitDepth::Eight, &data)
+}
+
+/// Writes the film as a 16-bit RGB PNG image.
+pub fn write_png16<W: Write>(film: &Film, out: W) -> io::Result<()> {
+    // PNG stores 16-bit samples big endian.
+    let data: Vec<u8> = film.pixels().iter()
+        .flat_map(|pixel| pixel.mean().to_rgb16())
+        .flat_map(|channel| channel.to_be_bytes())
+        .collect();
+
+    write_png(film, out, BitDepth::Sixteen, &data)
+}
+
+fn write_png<W: Write>(film: &Film, out: W, bit_depth: BitDepth, data: &[u8]) -> io::Result<()> {
+    let mut encoder = Encoder::new(out, film.width() as u32, film.height() as u32);
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(bit_depth);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Reads a PNG image of any color type and bit depth. Values are scaled to
+/// [0, 1] but otherwise left as stored, so colors are usually still sRGB
+/// encoded. Alpha is dropped.
+pub fn read_png<R: Read>(input: R) -> io::Result<Image> {
+    let mut decoder = Decoder::new(input);
+    // Expand palettes and low bit depths to whole bytes.
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?;
+
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?;
+    let data = &data[..info.buffer_size()];
+
+    let samples: Vec<f32> = match info.bit_depth {
+        BitDepth::Sixteen => data.chunks_exact(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as f32 / 65535.0)
+            .collect(),
+        _ => data.iter().map(|byte| *byte as f32 / 255.0).collect(),
+    };
+
+    let channels = info.color_type.samples();
+    let pixels = samples.chunks_exact(channels)
+        .map(|pixel| match info.color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => Color::new(pixel[0], pixel[0], pixel[0]),
+            _ => Color::new(pixel[0], pixel[1], pixel[2]),
+        })
+        .collect();
+
+    Ok(Image::new(info.width as usize, info.height as usize, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+    use crate::film::Film;
+    use crate::png::{read_png, write_png16, write_png8};
+
+    // Black and white along the top row, a quarter red and a quarter blue along
+    // the bottom, which gamma 2 takes to half values.
+    fn film() -> Film {
+        let mut film = Film::new(2, 2);
+        film.add_sample(1, 0, Color::new(1.0, 1.0, 1.0));
+        film.add_sample(0, 1, Color::new(0.25, 0.0, 0.0));
+        film.add_sample(1, 1, Color::new(0.0, 0.0, 0.25));
+        film
+    }
+
+    // The signature, then an IHDR chunk of 13 bytes for a 2 by 2 RGB image.
+    fn assert_header(data: &[u8], bit_depth: u8) {
+        assert_eq!(&data[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&data[8..12], &13u32.to_be_bytes());
+        assert_eq!(&data[12..16], b"IHDR");
+        assert_eq!(&data[16..20], &2u32.to_be_bytes());
+        assert_eq!(&data[20..24], &2u32.to_be_bytes());
+        // Bit depth, colour type 2 for RGB, then deflate, adaptive filtering
+        // and no interlacing.
+        assert_eq!(&data[24..29], &[bit_depth, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn eight_bit_images_have_rgb_header_and_gamma_encoded_pixels() {
+        let mut data = vec![];
+        write_png8(&film(), &mut data).unwrap();
+        assert_header(&data, 8);
+
+        let image = read_png(data.as_slice()).unwrap();
+        assert_eq!(image.pixel(0, 0).x(), 0.0);
+        assert_eq!(image.pixel(1, 0).y(), 1.0);
+        assert_eq!(image.pixel(0, 1).x(), 128.0 / 255.0);
+        assert_eq!(image.pixel(1, 1).z(), 128.0 / 255.0);
+    }
+
+    #[test]
+    fn sixteen_bit_images_have_rgb_header_and_gamma_encoded_pixels() {
+        let mut data = vec![];
+        write_png16(&film(), &mut data).unwrap();
+        assert_header(&data, 16);
+
+        let image = read_png(data.as_slice()).unwrap();
+        assert_eq!(image.pixel(1, 0).z(), 1.0);
+        assert_eq!(image.pixel(0, 1).x(), 32768.0 / 65535.0);
+        assert_eq!(image.pixel(1, 1).y(), 0.0);
+    }
+}
@@ -0,0 +1,99 @@
+use std::io;
+use std::io::Write;
+
+use crate::film::Film;
+use crate::utils::clamp;
+
+/// Writes the film as an ASCII PPM (P3) image.
+pub fn write_p3<W: Write>(film: &Film, out: &mut W) -> io::Result<()> {
+    writeln!(out, "P3")?;
+    writeln!(out, "{} {}", film.width(), film.height())?;
+    writeln!(out, "255")?;
+
+    for pixel in film.pixels() {
+        let [r, g, b] = pixel.mean().to_rgb8();
+        writeln!(out, "{} {} {}", r, g, b)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the film as a binary PPM (P6) image.
+pub fn write_p6<W: Write>(film: &Film, out: &mut W) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", film.width(), film.height())?;
+
+    for pixel in film.pixels() {
+        out.write_all(&pixel.mean().to_rgb8())?;
+    }
+
+    Ok(())
+}
+
+/// Writes the film's luminance as a binary PGM (P5) image.
+pub fn write_p5_luminance<W: Write>(film: &Film, out: &mut W) -> io::Result<()> {
+    write!(out, "P5\n{} {}\n255\n", film.width(), film.height())?;
+
+    for pixel in film.pixels() {
+        out.write_all(&[(256.0 * clamp(pixel.mean().luminance().sqrt(), 0.0, 0.999)) as u8])?;
+    }
+
+    Ok(())
+}
+
+/// Writes a single channel buffer, such as depth, as a binary PGM (P5) image.
+/// Values are mapped linearly from `[0, max_value]` to the full 8-bit range.
+pub fn write_p5<W: Write>(width: i32, height: i32, values: &[f32], max_value: f32, out: &mut W) -> io::Result<()> {
+    assert_eq!(values.len(), (width * height) as usize, "value count does not match image size");
+    write!(out, "P5\n{} {}\n255\n", width, height)?;
+
+    let data: Vec<u8> = values.iter()
+        .map(|value| (255.0 * clamp(value / max_value, 0.0, 1.0)).round() as u8)
+        .collect();
+    out.write_all(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+    use crate::film::Film;
+    use crate::ppm::{write_p5, write_p5_luminance, write_p6};
+
+    fn film() -> Film {
+        let mut film = Film::new(2, 2);
+        film.add_sample(1, 0, Color::new(1.0, 1.0, 1.0));
+        film.add_sample(0, 1, Color::new(0.25, 0.0, 1.0));
+        film
+    }
+
+    #[test]
+    fn p6_has_a_header_then_three_bytes_a_pixel() {
+        let mut data = vec![];
+        write_p6(&film(), &mut data).unwrap();
+
+        let header = b"P6\n2 2\n255\n";
+        assert_eq!(&data[..header.len()], header);
+        assert_eq!(&data[header.len()..], &[0, 0, 0, 255, 255, 255, 128, 0, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn p5_luminance_has_a_header_then_one_byte_a_pixel() {
+        let mut data = vec![];
+        write_p5_luminance(&film(), &mut data).unwrap();
+
+        let header = b"P5\n2 2\n255\n";
+        assert_eq!(&data[..header.len()], header);
+        assert_eq!(data.len(), header.len() + 4);
+        assert_eq!(&data[header.len()..header.len() + 2], &[0, 255]);
+    }
+
+    #[test]
+    fn p5_maps_single_channel_values_onto_bytes() {
+        let mut data = vec![];
+        write_p5(2, 2, &[0.0, 5.0, 10.0, 20.0], 10.0, &mut data).unwrap();
+
+        let header = b"P5\n2 2\n255\n";
+        assert_eq!(&data[..header.len()], header);
+        // Values beyond `max_value` saturate.
+        assert_eq!(&data[header.len()..], &[0, 128, 255, 255]);
+    }
+}
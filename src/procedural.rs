@@ -0,0 +1,244 @@
+use std::f32::consts::PI;
+
+use crate::{Color, Point3, Vec3};
+use crate::noise::{fbm, turbulence, Noise, Perlin, Worley};
+use crate::texture::Texture;
+
+/// How layers of noise are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fractal {
+    /// A single layer.
+    Plain,
+    Fbm { octaves: u32 },
+    Turbulence { octaves: u32 },
+}
+
+/// Noise in space shaded from `low` to `high`, black to white unless told
+/// otherwise. Grey noise suits scalar parameters such as roughness.
+pub struct NoiseTexture {
+    noise: Box<dyn Noise>,
+    scale: f32,
+    fractal: Fractal,
+    low: Color,
+    high: Color,
+}
+
+impl NoiseTexture {
+    /// `scale` is the frequency of the noise, in features per unit.
+    pub fn new(noise: Box<dyn Noise>, scale: f32) -> NoiseTexture {
+        NoiseTexture {
+            noise,
+            scale,
+            fractal: Fractal::Plain,
+            low: Color::new(0.0, 0.0, 0.0),
+            high: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn with_fractal(mut self, fractal: Fractal) -> NoiseTexture {
+        self.fractal = fractal;
+        self
+    }
+
+    pub fn with_colors(mut self, low: Color, high: Color) -> NoiseTexture {
+        self.low = low;
+        self.high = high;
+        self
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f32, _v: f32, p: &Point3) -> Color {
+        let p = p * self.scale;
+        // Signed noise is centred on the middle of the range; turbulence
+        // starts from its bottom.
+        let t = match self.fractal {
+            Fractal::Plain => 0.5 + 0.5 * self.noise.noise(&p),
+            Fractal::Fbm { octaves } => 0.5 + 0.5 * fbm(&*self.noise, &p, octaves),
+            Fractal::Turbulence { octaves } => turbulence(&*self.noise, &p, octaves),
+        };
+        mix(&self.low, &self.high, t.clamp(0.0, 1.0))
+    }
+}
+
+/// Veins running across the x axis, bent by turbulence.
+pub struct Marble {
+    noise: Perlin,
+    scale: f32,
+    base: Color,
+    vein: Color,
+}
+
+impl Marble {
+    /// `scale` sets how many veins lie along each unit of x, roughly.
+    pub fn new(seed: u64, scale: f32, base: Color, vein: Color) -> Marble {
+        Marble { noise: Perlin::new(seed), scale, base, vein }
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, _u: f32, _v: f32, p: &Point3) -> Color {
+        let phase = self.scale * PI * p.x() + 10.0 * turbulence(&self.noise, &(p * self.scale), 7);
+        // Veins are the narrow troughs of the wave.
+        let t = (0.5 + 0.5 * phase.sin()).powf(0.3);
+        mix(&self.vein, &self.base, t)
+    }
+}
+
+/// Growth rings around the y axis, with a fine grain running along it.
+pub struct Wood {
+    noise: Perlin,
+    rings: f32,
+    light: Color,
+    dark: Color,
+}
+
+impl Wood {
+    /// `rings` is the number of rings to each unit of radius.
+    pub fn new(seed: u64, rings: f32, light: Color, dark: Color) -> Wood {
+        Wood { noise: Perlin::new(seed), rings, light, dark }
+    }
+}
+
+impl Texture for Wood {
+    fn value(&self, _u: f32, _v: f32, p: &Point3) -> Color {
+        // Stretching the noise along the trunk draws it out into grain.
+        let grain_point = Vec3::new(p.x() * self.rings, p.y() * self.rings * 0.1, p.z() * self.rings);
+        let radius = (p.x() * p.x() + p.z() * p.z()).sqrt() * self.rings + 0.4 * fbm(&self.noise, &grain_point, 4);
+        let ring = radius - radius.floor();
+
+        // Each ring ends in a band of dark late wood.
+        let t = ring.powi(4) + 0.15 * fbm(&self.noise, &(&grain_point * 8.0), 2);
+        mix(&self.light, &self.dark, t.clamp(0.0, 1.0))
+    }
+}
+
+/// Interlocking crystals, some dark, with mottling across each and dark seams
+/// between them.
+pub struct Granite {
+    cells: Worley,
+    noise: Perlin,
+    scale: f32,
+    base: Color,
+    speckle: Color,
+}
+
+impl Granite {
+    /// `scale` is the number of crystals to each unit, roughly.
+    pub fn new(seed: u64, scale: f32, base: Color, speckle: Color) -> Granite {
+        Granite { cells: Worley::new(seed), noise: Perlin::new(seed.wrapping_add(1)), scale, base, speckle }
+    }
+}
+
+impl Texture for Granite {
+    fn value(&self, _u: f32, _v: f32, p: &Point3) -> Color {
+        let p = p * self.scale;
+        let crystal = self.cells.sample(&p);
+
+        // About a third of the crystals are of the darker mineral.
+        let color = if crystal.cell_value < 0.35 { &self.speckle } else { &self.base };
+        let mottling = 0.85 + 0.3 * fbm(&self.noise, &(&p * 4.0), 3);
+        let seam = ((crystal.f2 - crystal.f1) / 0.08).min(1.0);
+        color * (mottling * (0.6 + 0.4 * seam)).max(0.0)
+    }
+}
+
+fn mix(a: &Color, b: &Color, t: f32) -> Color {
+    a * (1.0 - t) + b * t
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Color, Point3, Vec3};
+    use crate::hittable::HitRecord;
+    use crate::material::{Lambertian, Material, Metal};
+    use crate::noise::{Noise, Perlin, Simplex, Worley};
+    use crate::procedural::{Fractal, Granite, Marble, NoiseTexture, Wood};
+    use crate::ray::Ray;
+    use crate::texture::Texture;
+    use crate::utils::seed_rng;
+
+    fn points() -> impl Iterator<Item = Point3> {
+        (0..64).map(|i| Point3::new(0.37 * i as f32 - 3.0, 0.23 * (i % 7) as f32, 0.11 * (i % 13) as f32 - 0.5))
+    }
+
+    fn textures(seed: u64) -> Vec<Box<dyn Texture>> {
+        let (light, dark) = (Color::new(0.9, 0.8, 0.7), Color::new(0.2, 0.1, 0.1));
+        vec![
+            Box::new(Marble::new(seed, 2.0, light.clone(), dark.clone())),
+            Box::new(Wood::new(seed, 3.0, light.clone(), dark.clone())),
+            Box::new(Granite::new(seed, 4.0, light, dark)),
+            Box::new(NoiseTexture::new(Box::new(Perlin::new(seed)), 2.0).with_fractal(Fractal::Fbm { octaves: 4 })),
+        ]
+    }
+
+    #[test]
+    fn patterns_are_fixed_by_their_seed() {
+        for ((a, b), c) in textures(7).iter().zip(textures(7)).zip(textures(8)) {
+            let mut differs = false;
+            for p in points() {
+                let value = a.value(0.0, 0.0, &p);
+                assert!((&value - &b.value(0.0, 0.0, &p)).length() == 0.0);
+                differs |= (&value - &c.value(0.0, 0.0, &p)).length() > 1e-4;
+            }
+            assert!(differs);
+        }
+    }
+
+    #[test]
+    fn noise_stays_between_its_colors() {
+        let (low, high) = (Color::new(0.2, 0.5, 0.1), Color::new(0.6, 0.5, 0.9));
+        for fractal in [Fractal::Plain, Fractal::Fbm { octaves: 6 }, Fractal::Turbulence { octaves: 6 }] {
+            let noises: [Box<dyn Noise>; 3] =
+                [Box::new(Perlin::new(1)), Box::new(Simplex::new(1)), Box::new(Worley::new(1))];
+            for noise in noises {
+                let texture = NoiseTexture::new(noise, 3.0).with_fractal(fractal).with_colors(low.clone(), high.clone());
+                for p in points() {
+                    let value = texture.value(0.0, 0.0, &p);
+                    assert!(value.x() >= 0.2 - 1e-6 && value.x() <= 0.6 + 1e-6);
+                    assert!((value.y() - 0.5).abs() < 1e-6);
+                    assert!(value.z() >= 0.1 - 1e-6 && value.z() <= 0.9 + 1e-6);
+                }
+            }
+        }
+    }
+
+    fn hit_on(material: &dyn Material, p: Point3) -> HitRecord<'_> {
+        HitRecord {
+            p,
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            material,
+            front_face: true,
+            is_hit: true,
+            ..HitRecord::miss()
+        }
+    }
+
+    #[test]
+    fn patterns_serve_as_albedo_and_roughness() {
+        seed_rng(24);
+        let marble: Arc<dyn Texture> = Arc::new(Marble::new(3, 2.0, Color::new(0.9, 0.9, 0.9), Color::new(0.1, 0.1, 0.3)));
+        let lambertian = Lambertian::textured(Arc::clone(&marble));
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        for p in points() {
+            let rec = hit_on(&lambertian, p.clone());
+            let (_, attenuation, scattered) = lambertian.scatter(&ray, &rec);
+            assert!(scattered);
+            assert!((&attenuation - &marble.value(0.0, 0.0, &p)).length() < 1e-6);
+        }
+
+        // Fuzz never strays further than the roughness texture allows.
+        let roughness = NoiseTexture::new(Box::new(Perlin::new(5)), 4.0)
+            .with_colors(Color::new(0.0, 0.0, 0.0), Color::new(0.2, 0.2, 0.2));
+        let metal = Metal::textured(Arc::clone(&marble), Arc::new(roughness));
+        let reflected = Vec3::new(1.0, 1.0, 0.0).unit_vector();
+        for p in points() {
+            let rec = hit_on(&metal, p.clone());
+            let (scattered, attenuation, _) = metal.scatter(&ray, &rec);
+            assert!((&attenuation - &marble.value(0.0, 0.0, &p)).length() < 1e-6);
+            assert!((&scattered.dir - &reflected).length() <= 0.2 + 1e-5);
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::film::{Film, FilmPixel};
+use crate::hittable::{HitRecord, Hittable};
+use crate::light::Lights;
+use crate::ray::Ray;
+use crate::utils::{random_float, seed_rng, stream_seed};
+
+pub const DEFAULT_SEED: u64 = 0;
+
+pub struct ImageProperties {
+    pub image_height: i32,
+    pub image_width: i32,
+}
+
+pub struct RenderProperties {
+    pub samples_per_pixel: i32,
+    pub max_depth: i8,
+    pub seed: u64,
+    /// The number of worker threads, or `None` to use every available core.
+    pub thread_count: Option<usize>,
+}
+
+pub struct Renderer<H: Hittable, L: Hittable> {
+    world: H,
+    lights: Lights<L>,
+    camera: Camera,
+    background: Background,
+    image_properties: ImageProperties,
+    render_properties: RenderProperties,
+}
+
+impl<H: Hittable, L: Hittable> Renderer<H, L> {
+    /// `lights` holds the emissive shapes of the world and the lights without
+    /// geometry, all of which are sampled explicitly.
+    pub fn new(
+        world: H,
+        lights: Lights<L>,
+        camera: Camera,
+        background: Background,
+        image_properties: ImageProperties,
+        render_properties: RenderProperties,
+    ) -> Renderer<H, L> {
+        Renderer { world, lights, camera, background, image_properties, render_properties }
+    }
+
+    pub fn render(&self) -> Film {
+        self.render_with_progress(|_, _| {})
+    }
+
+    /// Renders the image on all available cores. `progress` is called from the
+    /// worker threads with the number of finished and total scanlines.
+    pub fn render_with_progress<P: Fn(usize, usize) + Sync>(&self, progress: P) -> Film {
+        let image_height = self.image_properties.image_height as usize;
+        // There is no work for more threads than scanlines.
+        let thread_count = self.render_properties.thread_count
+            .unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+            .clamp(1, image_height.max(1));
+
+        let next_scanline = AtomicUsize::new(0);
+        let scanlines_done = AtomicUsize::new(0);
+
+        let mut scanlines: Vec<(usize, Vec<FilmPixel>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..thread_count)
+                .map(|_| scope.spawn(|| {
+                    let mut rendered = vec![];
+                    loop {
+                        let row = next_scanline.fetch_add(1, Ordering::Relaxed);
+                        if row >= image_height {
+                            break;
+                        }
+
+                        let j = (image_height - 1 - row) as i32;
+                        rendered.push((row, self.render_scanline(j)));
+
+                        let done = scanlines_done.fetch_add(1, Ordering::Relaxed) + 1;
+                        progress(done, image_height);
+                    }
+                    rendered
+                }))
+                .collect();
+
+            workers.into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        scanlines.sort_by_key(|(row, _)| *row);
+        Film::from_pixels(
+            self.image_properties.image_width,
+            self.image_properties.image_height,
+            scanlines.into_iter().flat_map(|(_, pixels)| pixels).collect(),
+        )
+    }
+
+    fn render_scanline(&self, j: i32) -> Vec<FilmPixel> {
+        let image_properties = &self.image_properties;
+        let render_properties = &self.render_properties;
+
+        (0..image_properties.image_width)
+            .map(|i| {
+                // Every pixel samples from its own stream so the image does not depend on
+                // which thread rendered it.
+                let pixel_index = j as u64 * image_properties.image_width as u64 + i as u64;
+                seed_rng(stream_seed(render_properties.seed, pixel_index));
+
+                let mut pixel = FilmPixel::new();
+                for _s in 0..render_properties.samples_per_pixel {
+                    let u = ((i as f32) + random_float()) / (image_properties.image_width - 1) as f32;
+                    let v = ((j as f32) + random_float()) / (image_properties.image_height - 1) as f32;
+
+                    let r = self.camera.get_ray(u, v);
+
+                    pixel.add_sample(ray_color(&r, &self.world, &self.lights, &self.background, render_properties.max_depth));
+                }
+                pixel
+            })
+            .collect()
+    }
+}
+
+/// Estimates the radiance arriving along `ray`. Light from emissive shapes
+/// reaching diffuse surfaces is gathered both by sampling the shapes directly
+/// and by following the scattered ray, and the two estimates are combined with
+/// multiple importance sampling. Delta lights can only be sampled directly.
+pub fn ray_color<H: Hittable, L: Hittable>(ray: &Ray, world: &H, lights: &Lights<L>, background: &Background, depth: i8) -> Color {
+    radiance(ray, world, lights, background, depth, None)
+}
+
+// `scatter_pdf` is the density with which the previous bounce chose `ray`, or
+// `None` if it was a camera ray or a specular bounce that lights could not
+// have been sampled for.
+fn radiance<H: Hittable, L: Hittable>(
+    ray: &Ray,
+    world: &H,
+    lights: &Lights<L>,
+    background: &Background,
+    depth: i8,
+    scatter_pdf: Option<f32>,
+) -> Color {
+    // If we've exceeded the ray bounce limit, no more light is gathered.
+    if depth <= 0 {
+        return Color::origin();
+    }
+
+    let mut rec = world.hit(ray, 0.001, f32::INFINITY);
+    if !rec.is_hit {
+        let color = background.color(ray);
+        return match scatter_pdf {
+            Some(scatter_pdf) if background.pdf_value(&ray.dir) > 0.0 => {
+                color * power_heuristic(scatter_pdf, background.pdf_value(&ray.dir))
+            }
+            Some(_) => color,
+            // Distant lights such as the sun are only seen directly or in mirrors;
+            // diffuse bounces already gathered them by sampling.
+            None => lights.delta.iter().fold(color, |color, light| color + light.emitted(&ray.dir)),
+        };
+    }
+
+    let material = rec.material;
+    material.perturb_normal(&mut rec);
+
+    let mut emitted = rec.material.emitted(&rec);
+    if let Some(scatter_pdf) = scatter_pdf {
+        if !emitted.near_zero() {
+            emitted *= power_heuristic(scatter_pdf, lights.shapes.pdf_value(&ray.orig, &ray.dir));
+        }
+    }
+
+    let (scattered, attenuation, was_scattered) = rec.material.scatter(ray, &rec);
+    if !was_scattered {
+        return emitted;
+    }
+
+    let scatter_pdf = rec.material.pdf(ray, &rec, &scattered.dir);
+    if scatter_pdf <= 0.0 {
+        return emitted + radiance(&scattered, world, lights, background, depth - 1, None) * attenuation;
+    }
+
+    emitted
+        + sample_lights(ray, &rec, world, &lights.shapes)
+        + sample_delta_lights(ray, &rec, world, lights)
+        + sample_background(ray, &rec, world, background)
+        + radiance(&scattered, world, lights, background, depth - 1, Some(scatter_pdf)) * attenuation
+}
+
+/// Traces a shadow ray towards a random point on one of the lights and returns
+/// the light it carries, weighted against the chance of scattering that way.
+fn sample_lights<H: Hittable, L: Hittable>(ray_in: &Ray, rec: &HitRecord, world: &H, lights: &L) -> Color {
+    let direction = lights.random_direction(&rec.p);
+    let light_pdf = lights.pdf_value(&rec.p, &direction);
+    if light_pdf <= 0.0 {
+        return Color::origin();
+    }
+
+    let f = rec.material.eval(ray_in, rec, &direction);
+    if f.near_zero() {
+        return Color::origin();
+    }
+
+    let shadow_ray = Ray::new(rec.p.clone(), direction);
+    let shadow_rec = world.hit(&shadow_ray, 0.001, f32::INFINITY);
+    if !shadow_rec.is_hit {
+        return Color::origin();
+    }
+
+    let light = shadow_rec.material.emitted(&shadow_rec);
+    let scatter_pdf = rec.material.pdf(ray_in, rec, &shadow_ray.dir);
+    f * light * (power_heuristic(light_pdf, scatter_pdf) / light_pdf)
+}
+
+/// Traces a shadow ray in a direction drawn from the background and returns the
+/// light arriving along it if it escapes the scene.
+fn sample_background<H: Hittable>(ray_in: &Ray, rec: &HitRecord, world: &H, background: &Background) -> Color {
+    let direction = background.random_direction();
+    let background_pdf = background.pdf_value(&direction);
+    if background_pdf <= 0.0 {
+        return Color::origin();
+    }
+
+    let f = rec.material.eval(ray_in, rec, &direction);
+    if f.near_zero() {
+        return Color::origin();
+    }
+
+    let shadow_ray = Ray::new(rec.p.clone(), direction);
+    if world.hit(&shadow_ray, 0.001, f32::INFINITY).is_hit {
+        return Color::origin();
+    }
+
+    let scatter_pdf = rec.material.pdf(ray_in, rec, &shadow_ray.dir);
+    f * background.color(&shadow_ray) * (power_heuristic(background_pdf, scatter_pdf) / background_pdf)
+}
+
+/// Traces a shadow ray towards every delta light and sums the light they deliver.
+fn sample_delta_lights<H: Hittable, L: Hittable>(ray_in: &Ray, rec: &HitRecord, world: &H, lights: &Lights<L>) -> Color {
+    let mut total = Color::origin();
+    for light in lights.delta.iter() {
+        let sample = light.sample(&rec.p);
+        if sample.irradiance.near_zero() {
+            continue;
+        }
+
+        let f = rec.material.eval(ray_in, rec, &sample.direction);
+        if f.near_zero() {
+            continue;
+        }
+
+        let shadow_ray = Ray::new(rec.p.clone(), sample.direction.clone());
+        if world.hit(&shadow_ray, 0.001, sample.distance * (1.0 - 1e-4)).is_hit {
+            continue;
+        }
+
+        total += f * sample.irradiance;
+    }
+    total
+}
+
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let a = pdf * pdf;
+    let b = other_pdf * other_pdf;
+    a / (a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Point3, Vec3};
+    use crate::background::Background;
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::film::Film;
+    use crate::hittable_list::HittableList;
+    use crate::light::Lights;
+    use crate::material::{DiffuseLight, Lambertian, Metal};
+    use crate::ray::Ray;
+    use crate::renderer::{ray_color, ImageProperties, RenderProperties, Renderer};
+    use crate::sphere::Sphere;
+    use crate::utils::seed_rng;
+
+    fn light() -> Sphere<'static> {
+        Sphere::new(Point3::new(0.0, 3.0, 0.0), 0.5, Box::new(DiffuseLight::new(Color::new(8.0, 8.0, 8.0))))
+    }
+
+    fn world() -> HittableList<Sphere<'static>> {
+        HittableList::new()
+            .add(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))))
+            .add(Sphere::new(Point3::new(1.5, 0.5, 0.0), 0.5, Box::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)))))
+            .add(Sphere::new(Point3::new(-1.5, 0.5, 0.0), 0.5, Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.2))))
+            .add(light())
+    }
+
+    fn mean_radiance(ray: &Ray, lights: &Lights<HittableList<Sphere<'static>>>, samples: i32) -> Color {
+        let world = world();
+        let background = Background::Solid(Color::origin());
+
+        let mut sum = Color::origin();
+        for _ in 0..samples {
+            sum += ray_color(ray, &world, lights, &background, 8);
+        }
+        sum / samples as f32
+    }
+
+    #[test]
+    fn light_sampling_converges_to_brute_force_reference() {
+        let rays = [
+            Ray::new(Point3::new(0.0, 1.0, 5.0), Vec3::new(0.0, -1.0, -5.0)),
+            Ray::new(Point3::new(0.0, 1.0, 5.0), Vec3::new(1.5, -0.5, -5.0)),
+            Ray::new(Point3::new(0.0, 1.0, 5.0), Vec3::new(-1.0, -1.0, -3.0)),
+        ];
+
+        for ray in rays.iter() {
+            seed_rng(1);
+            let reference = mean_radiance(ray, &Lights::new(HittableList::new()), 400_000);
+            seed_rng(2);
+            let estimate = mean_radiance(ray, &Lights::new(HittableList::new().add(light())), 100_000);
+
+            for channel in 0..3 {
+                let relative_error = (estimate[channel] - reference[channel]).abs() / reference[channel];
+                assert!(
+                    relative_error < 0.05,
+                    "channel {}: light sampled {} against reference {}",
+                    channel, estimate[channel], reference[channel]
+                );
+            }
+        }
+    }
+
+    fn render(thread_count: usize) -> Film {
+        let camera = Camera::new(
+            Point3::new(0.0, 1.0, 5.0),
+            Point3::new(0.0, 0.5, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.5,
+            0.1,
+            5.0,
+        );
+        Renderer::new(
+            world(),
+            Lights::new(HittableList::new().add(light())),
+            camera,
+            Background::Solid(Color::new(0.1, 0.1, 0.2)),
+            ImageProperties { image_width: 12, image_height: 8 },
+            RenderProperties { samples_per_pixel: 4, max_depth: 8, seed: 7, thread_count: Some(thread_count) },
+        )
+        .render()
+    }
+
+    #[test]
+    fn images_do_not_depend_on_the_thread_count() {
+        let bits = |film: &Film| -> Vec<u32> {
+            film.pixels().iter()
+                .flat_map(|pixel| [pixel.sum.x(), pixel.sum.y(), pixel.sum.z(), pixel.sum_squared.x()])
+                .map(f32::to_bits)
+                .collect()
+        };
+
+        let single = bits(&render(1));
+        assert!(single.iter().any(|value| *value != 0));
+        assert_eq!(single, bits(&render(4)));
+        assert_eq!(single, bits(&render(7)));
+        // More threads than scanlines.
+        assert_eq!(single, bits(&render(1000)));
+    }
+}
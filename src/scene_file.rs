@@ -0,0 +1,1239 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{Point3, Vec3};
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::environment::EnvironmentMap;
+use crate::gltf::load_gltf;
+use crate::hittable::Hittable;
+use crate::hittable_list::World;
+use crate::light::{Light, Lights};
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::noise::{Noise, Perlin, Simplex, Worley};
+use crate::normal_map::{BumpMapped, NormalMapped};
+use crate::obj::load_obj;
+use crate::ply::load_ply;
+use crate::procedural::{Fractal, Granite, Marble, NoiseTexture, Wood};
+use crate::sky::Sky;
+use crate::sphere::Sphere;
+use crate::stl::load_stl;
+use crate::texture::{Checker2D, Checker3D, Filter, ImageTexture, SolidColor, Texture, WrapMode};
+use crate::transform::{Transform, Transformed};
+use crate::triangle::Triangle;
+use crate::triangle_mesh::TriangleMesh;
+
+/// A scene loaded from a TOML scene description.
+///
+/// ```toml
+/// [camera]
+/// look_from = [13.0, 2.0, 3.0]
+/// look_at = [0.0, 0.0, 0.0]
+/// vertical_fov = 20.0
+/// aspect_ratio = 1.5
+/// aperture = 0.1
+/// focus_distance = 10.0
+///
+/// [background]
+/// type = "gradient"
+/// bottom = [1.0, 1.0, 1.0]
+/// top = [0.5, 0.7, 1.0]
+///
+/// [textures.checks]
+/// type = "checker"
+/// even = [0.2, 0.3, 0.1]
+/// odd = [0.9, 0.9, 0.9]
+/// squares = 20.0
+///
+/// [materials.ground]
+/// type = "lambertian"
+/// albedo = "checks"
+///
+/// [[spheres]]
+/// center = [0.0, -1000.0, 0.0]
+/// radius = 1000.0
+/// material = "ground"
+///
+/// [[triangles]]
+/// vertices = [[-1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 2.0, 0.0]]
+/// material = "ground"
+///
+/// [[meshes]]
+/// path = "bunny.ply"
+/// material = "ground"
+/// rotate = [0.0, 45.0, 0.0]
+/// translate = [2.0, 0.0, 0.0]
+///
+/// [[lights]]
+/// type = "directional"
+/// direction = [-1.0, -2.0, -0.5]
+/// irradiance = [3.0, 3.0, 3.0]
+/// angular_diameter = 0.5
+/// ```
+///
+/// The camera can instead be taken from a glTF file, relative to the scene
+/// file, by its index among the file's perspective cameras:
+///
+/// ```toml
+/// camera = { gltf = "room.gltf", index = 0 }
+/// ```
+///
+/// Spheres, triangles and meshes with an emissive material are sampled
+/// directly, like the `[[lights]]`.
+pub struct SceneFile {
+    pub camera: CameraDescription,
+    pub background: Background,
+    pub world: World,
+    /// The described lights, plus every emissive sphere, triangle and mesh in
+    /// `world` for sampling them directly.
+    pub lights: Lights<World>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub look_from: [f32; 3],
+    pub look_at: [f32; 3],
+    #[serde(default = "default_view_up")]
+    pub view_up: [f32; 3],
+    pub vertical_fov: f32,
+    pub aspect_ratio: f32,
+    #[serde(default)]
+    pub aperture: f32,
+    /// Defaults to the distance between `look_from` and `look_at`.
+    pub focus_distance: Option<f32>,
+}
+
+impl CameraDescription {
+    /// Builds the camera, using `aspect_ratio` in place of the described one so
+    /// the image size can be chosen independently of the scene.
+    pub fn build(&self, aspect_ratio: f32) -> Camera {
+        let look_from = vec3(self.look_from);
+        let look_at = vec3(self.look_at);
+        let focus_distance = self.focus_distance.unwrap_or_else(|| (&look_from - &look_at).length());
+
+        Camera::new(
+            look_from,
+            look_at,
+            vec3(self.view_up),
+            self.vertical_fov,
+            aspect_ratio,
+            self.aperture,
+            focus_distance,
+        )
+    }
+
+    /// Checks that the camera can be built, as a zero view direction or a
+    /// `view_up` along it would leave the camera without an orientation.
+    fn check(&self) -> Result<(), String> {
+        let direction = &vec3(self.look_at) - &vec3(self.look_from);
+        if direction.length_squared() == 0.0 {
+            return Err("look_from and look_at must differ".to_string());
+        }
+        let view_up = vec3(self.view_up);
+        if view_up.cross(&direction).length_squared() <= 1e-12 * view_up.length_squared() * direction.length_squared() {
+            return Err("view_up must not be parallel to the view direction".to_string());
+        }
+        if !(self.vertical_fov > 0.0 && self.vertical_fov < 180.0) {
+            return Err("vertical_fov must be between 0 and 180 degrees".to_string());
+        }
+        if self.aspect_ratio.is_nan() || self.aspect_ratio <= 0.0 {
+            return Err("aspect_ratio must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn default_view_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected a camera, or a glTF file and the index of a camera in it")]
+enum CameraSource {
+    Described(CameraDescription),
+    Gltf(GltfCameraDescription),
+}
+
+/// A perspective camera of a glTF file, in the order `load_gltf` finds them.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GltfCameraDescription {
+    gltf: String,
+    #[serde(default)]
+    index: usize,
+}
+
+impl CameraSource {
+    fn build(self, directory: &Path) -> Result<CameraDescription, String> {
+        let camera = match self {
+            CameraSource::Described(camera) => camera,
+            CameraSource::Gltf(GltfCameraDescription { gltf, index }) => {
+                load_gltf(&directory.join(&gltf))
+                    .map_err(|error| error.to_string())?
+                    .cameras
+                    .into_iter()
+                    .nth(index)
+                    .ok_or_else(|| format!("'{}' has no perspective camera at index {}", gltf, index))?
+            }
+        };
+        camera.check()?;
+        Ok(camera)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: Spanned<CameraSource>,
+    background: Option<Spanned<BackgroundDescription>>,
+    #[serde(default)]
+    textures: HashMap<String, Spanned<TextureDescription>>,
+    #[serde(default)]
+    materials: Materials,
+    #[serde(default)]
+    spheres: Vec<SphereDescription>,
+    #[serde(default)]
+    triangles: Vec<TriangleDescription>,
+    #[serde(default)]
+    meshes: Vec<MeshDescription>,
+    #[serde(default)]
+    lights: Vec<Spanned<LightDescription>>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundDescription {
+    Solid {
+        color: [f32; 3],
+    },
+    Gradient {
+        bottom: [f32; 3],
+        top: [f32; 3],
+    },
+    /// An equirectangular .hdr or .pfm image, relative to the scene file.
+    Environment {
+        path: String,
+        /// Degrees about the vertical axis.
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// A daylight sky, with the sun added to the lights unless `sun` is false.
+    Sky {
+        /// Degrees above the horizon.
+        sun_elevation: f32,
+        /// Degrees about the vertical axis, with 0 towards -z and 90 towards +x.
+        #[serde(default)]
+        sun_azimuth: f32,
+        #[serde(default = "default_turbidity")]
+        turbidity: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+        #[serde(default = "default_sun")]
+        sun: bool,
+    },
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+fn default_sun() -> bool {
+    true
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+impl BackgroundDescription {
+    fn build(&self, directory: &Path) -> Result<Background, String> {
+        match self {
+            BackgroundDescription::Solid { color } => Ok(Background::Solid(vec3(*color))),
+            BackgroundDescription::Gradient { bottom, top } => Ok(Background::Gradient {
+                bottom: vec3(*bottom),
+                top: vec3(*top),
+            }),
+            BackgroundDescription::Environment { path, rotation, intensity } => {
+                EnvironmentMap::load(&directory.join(path), *rotation, *intensity)
+                    .map(Background::Environment)
+                    .map_err(|error| format!("cannot load environment map '{}': {}", path, error))
+            }
+            BackgroundDescription::Sky { sun_elevation, sun_azimuth, turbidity, intensity, .. } => {
+                Ok(Background::Sky(Sky::new(*sun_elevation, *sun_azimuth, *turbidity, *intensity)))
+            }
+        }
+    }
+}
+
+/// Image paths are relative to the scene file.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDescription {
+    Solid {
+        color: [f32; 3],
+    },
+    /// Squares in surface coordinates, `squares` to each unit of u and v.
+    Checker {
+        even: [f32; 3],
+        odd: [f32; 3],
+        #[serde(default = "default_squares")]
+        squares: f32,
+    },
+    /// Cubes of side `size` in space.
+    #[serde(rename = "checker_3d")]
+    Checker3D {
+        even: [f32; 3],
+        odd: [f32; 3],
+        #[serde(default = "default_size")]
+        size: f32,
+    },
+    /// A PNG or JPEG image is taken to be sRGB encoded unless `linear` is set,
+    /// as it should be for data such as roughness.
+    Image {
+        path: String,
+        #[serde(default)]
+        wrap: WrapDescription,
+        #[serde(default)]
+        filter: FilterDescription,
+        #[serde(default)]
+        linear: bool,
+    },
+    /// Noise in space, shaded from `low` to `high`. `octaves` above 1 layer it
+    /// as fBm, or as turbulence if `turbulence` is set.
+    Noise {
+        #[serde(default)]
+        noise: NoiseKind,
+        #[serde(default)]
+        seed: u64,
+        #[serde(default = "default_noise_scale")]
+        scale: f32,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default)]
+        turbulence: bool,
+        #[serde(default = "default_low")]
+        low: [f32; 3],
+        #[serde(default = "default_high")]
+        high: [f32; 3],
+    },
+    Marble {
+        #[serde(default)]
+        seed: u64,
+        #[serde(default = "default_noise_scale")]
+        scale: f32,
+        #[serde(default = "default_high")]
+        base: [f32; 3],
+        #[serde(default = "default_vein")]
+        vein: [f32; 3],
+    },
+    /// Rings around the y axis through the origin.
+    Wood {
+        #[serde(default)]
+        seed: u64,
+        #[serde(default = "default_rings")]
+        rings: f32,
+        #[serde(default = "default_light_wood")]
+        light: [f32; 3],
+        #[serde(default = "default_dark_wood")]
+        dark: [f32; 3],
+    },
+    Granite {
+        #[serde(default)]
+        seed: u64,
+        #[serde(default = "default_crystals")]
+        scale: f32,
+        #[serde(default = "default_granite")]
+        base: [f32; 3],
+        #[serde(default = "default_speckle")]
+        speckle: [f32; 3],
+    },
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NoiseKind {
+    #[default]
+    Perlin,
+    Simplex,
+    Worley,
+}
+
+fn default_noise_scale() -> f32 {
+    4.0
+}
+
+// Beyond this, layers are finer than an f32 can place.
+const MAX_OCTAVES: u32 = 24;
+
+fn default_octaves() -> u32 {
+    1
+}
+
+fn default_rings() -> f32 {
+    8.0
+}
+
+fn default_crystals() -> f32 {
+    20.0
+}
+
+fn default_low() -> [f32; 3] {
+    [0.0, 0.0, 0.0]
+}
+
+fn default_high() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_vein() -> [f32; 3] {
+    [0.2, 0.2, 0.25]
+}
+
+fn default_light_wood() -> [f32; 3] {
+    [0.65, 0.45, 0.25]
+}
+
+fn default_dark_wood() -> [f32; 3] {
+    [0.35, 0.2, 0.1]
+}
+
+fn default_granite() -> [f32; 3] {
+    [0.7, 0.65, 0.6]
+}
+
+fn default_speckle() -> [f32; 3] {
+    [0.15, 0.15, 0.15]
+}
+
+fn default_squares() -> f32 {
+    10.0
+}
+
+fn default_size() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WrapDescription {
+    #[default]
+    Repeat,
+    MirroredRepeat,
+    Clamp,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FilterDescription {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+impl TextureDescription {
+    fn build(&self, directory: &Path) -> Result<Arc<dyn Texture>, String> {
+        let solid = |color: &[f32; 3]| -> Arc<dyn Texture> { Arc::new(SolidColor::new(vec3(*color))) };
+        match self {
+            TextureDescription::Solid { color } => Ok(solid(color)),
+            TextureDescription::Checker { even, odd, squares } => {
+                Ok(Arc::new(Checker2D::new(solid(even), solid(odd), *squares)))
+            }
+            TextureDescription::Checker3D { even, odd, size } => {
+                if *size <= 0.0 {
+                    return Err("size must be positive".to_string());
+                }
+                Ok(Arc::new(Checker3D::new(solid(even), solid(odd), *size)))
+            }
+            TextureDescription::Image { path, wrap, filter, linear } => {
+                let full_path = directory.join(path);
+                let texture = if *linear { ImageTexture::load_linear(&full_path) } else { ImageTexture::load(&full_path) }
+                    .map_err(|error| format!("cannot load texture '{}': {}", path, error))?;
+                let wrap = match wrap {
+                    WrapDescription::Repeat => WrapMode::Repeat,
+                    WrapDescription::MirroredRepeat => WrapMode::MirroredRepeat,
+                    WrapDescription::Clamp => WrapMode::ClampToEdge,
+                };
+                let filter = match filter {
+                    FilterDescription::Nearest => Filter::Nearest,
+                    FilterDescription::Bilinear => Filter::Bilinear,
+                };
+                Ok(Arc::new(texture.with_wrap(wrap).with_filter(filter)))
+            }
+            TextureDescription::Noise { noise, seed, scale, octaves, turbulence, low, high } => {
+                if !(1..=MAX_OCTAVES).contains(octaves) {
+                    return Err(format!("octaves must be between 1 and {}", MAX_OCTAVES));
+                }
+                let noise: Box<dyn Noise> = match noise {
+                    NoiseKind::Perlin => Box::new(Perlin::new(*seed)),
+                    NoiseKind::Simplex => Box::new(Simplex::new(*seed)),
+                    NoiseKind::Worley => Box::new(Worley::new(*seed)),
+                };
+                let fractal = match (*octaves, *turbulence) {
+                    (octaves, true) => Fractal::Turbulence { octaves },
+                    (1, false) => Fractal::Plain,
+                    (octaves, false) => Fractal::Fbm { octaves },
+                };
+                Ok(Arc::new(NoiseTexture::new(noise, *scale).with_fractal(fractal).with_colors(vec3(*low), vec3(*high))))
+            }
+            TextureDescription::Marble { seed, scale, base, vein } => {
+                Ok(Arc::new(Marble::new(*seed, *scale, vec3(*base), vec3(*vein))))
+            }
+            TextureDescription::Wood { seed, rings, light, dark } => {
+                Ok(Arc::new(Wood::new(*seed, *rings, vec3(*light), vec3(*dark))))
+            }
+            TextureDescription::Granite { seed, scale, base, speckle } => {
+                Ok(Arc::new(Granite::new(*seed, *scale, vec3(*base), vec3(*speckle))))
+            }
+        }
+    }
+}
+
+/// A colour given directly or as the name of a texture.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected a colour [r, g, b] or the name of a texture")]
+enum ColorParameter {
+    Value([f32; 3]),
+    Texture(String),
+}
+
+/// A number given directly or as the name of a texture, whose red channel is
+/// used.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected a number or the name of a texture")]
+enum ScalarParameter {
+    Value(f32),
+    Texture(String),
+}
+
+type Textures = HashMap<String, Arc<dyn Texture>>;
+
+impl ColorParameter {
+    fn build(&self, textures: &Textures) -> Arc<dyn Texture> {
+        match self {
+            ColorParameter::Value(color) => Arc::new(SolidColor::new(vec3(*color))),
+            ColorParameter::Texture(name) => Arc::clone(&textures[name]),
+        }
+    }
+
+    fn texture(&self) -> Option<&str> {
+        match self {
+            ColorParameter::Value(_) => None,
+            ColorParameter::Texture(name) => Some(name),
+        }
+    }
+}
+
+impl ScalarParameter {
+    fn build(&self, textures: &Textures) -> Arc<dyn Texture> {
+        match self {
+            ScalarParameter::Value(value) => Arc::new(SolidColor::scalar(*value)),
+            ScalarParameter::Texture(name) => Arc::clone(&textures[name]),
+        }
+    }
+
+    fn texture(&self) -> Option<&str> {
+        match self {
+            ScalarParameter::Value(_) => None,
+            ScalarParameter::Texture(name) => Some(name),
+        }
+    }
+}
+
+// Read through `MaterialFields`, which also supplies the defaults.
+#[derive(Deserialize)]
+#[serde(try_from = "MaterialFields")]
+enum MaterialDescription {
+    Lambertian {
+        albedo: ColorParameter,
+    },
+    Metal {
+        albedo: ColorParameter,
+        fuzz: ScalarParameter,
+    },
+    Dielectric {
+        index_of_refraction: ScalarParameter,
+    },
+    DiffuseLight {
+        emit: ColorParameter,
+    },
+    /// Another material, with its normals read from a normal map texture. The
+    /// texture should be an image loaded with `linear = true`.
+    NormalMapped {
+        material: String,
+        map: String,
+        strength: f32,
+    },
+    /// Another material, with its surface raised by `scale` times the red
+    /// channel of a texture.
+    BumpMapped {
+        material: String,
+        height: String,
+        scale: f32,
+    },
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MaterialType {
+    Lambertian,
+    Metal,
+    Dielectric,
+    DiffuseLight,
+    NormalMapped,
+    BumpMapped,
+}
+
+// Every field any type of material can have. Reading materials through this
+// plain table, rather than an internally tagged enum that buffers the table
+// first, keeps the position of each field for reporting errors in its value.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialFields {
+    #[serde(rename = "type")]
+    material_type: MaterialType,
+    albedo: Option<ColorParameter>,
+    fuzz: Option<ScalarParameter>,
+    index_of_refraction: Option<ScalarParameter>,
+    emit: Option<ColorParameter>,
+    material: Option<String>,
+    map: Option<String>,
+    strength: Option<f32>,
+    height: Option<String>,
+    scale: Option<f32>,
+}
+
+impl TryFrom<MaterialFields> for MaterialDescription {
+    type Error = String;
+
+    fn try_from(mut fields: MaterialFields) -> Result<MaterialDescription, String> {
+        let description = match fields.material_type {
+            MaterialType::Lambertian => MaterialDescription::Lambertian {
+                albedo: required(&mut fields.albedo, "albedo")?,
+            },
+            MaterialType::Metal => MaterialDescription::Metal {
+                albedo: required(&mut fields.albedo, "albedo")?,
+                fuzz: fields.fuzz.take().unwrap_or_else(default_fuzz),
+            },
+            MaterialType::Dielectric => MaterialDescription::Dielectric {
+                index_of_refraction: required(&mut fields.index_of_refraction, "index_of_refraction")?,
+            },
+            MaterialType::DiffuseLight => MaterialDescription::DiffuseLight {
+                emit: required(&mut fields.emit, "emit")?,
+            },
+            MaterialType::NormalMapped => MaterialDescription::NormalMapped {
+                material: required(&mut fields.material, "material")?,
+                map: required(&mut fields.map, "map")?,
+                strength: fields.strength.take().unwrap_or_else(default_strength),
+            },
+            MaterialType::BumpMapped => MaterialDescription::BumpMapped {
+                material: required(&mut fields.material, "material")?,
+                height: required(&mut fields.height, "height")?,
+                scale: required(&mut fields.scale, "scale")?,
+            },
+        };
+
+        // Whatever was not taken above belongs to some other type of material.
+        let unused = [
+            ("albedo", fields.albedo.is_some()),
+            ("fuzz", fields.fuzz.is_some()),
+            ("index_of_refraction", fields.index_of_refraction.is_some()),
+            ("emit", fields.emit.is_some()),
+            ("material", fields.material.is_some()),
+            ("map", fields.map.is_some()),
+            ("strength", fields.strength.is_some()),
+            ("height", fields.height.is_some()),
+            ("scale", fields.scale.is_some()),
+        ];
+        match unused.iter().find(|(_, present)| *present) {
+            Some((name, _)) => Err(format!("field `{}` does not apply to this type of material", name)),
+            None => Ok(description),
+        }
+    }
+}
+
+fn required<T>(field: &mut Option<T>, name: &str) -> Result<T, String> {
+    field.take().ok_or_else(|| format!("missing field `{}`", name))
+}
+
+fn default_strength() -> f32 {
+    1.0
+}
+
+type Materials = HashMap<String, Spanned<MaterialDescription>>;
+
+fn default_fuzz() -> ScalarParameter {
+    ScalarParameter::Value(0.0)
+}
+
+impl MaterialDescription {
+    // Every object gets its own instance, so a named material can be used any
+    // number of times. The textures and materials it names must have been
+    // checked to exist.
+    fn build(&self, textures: &Textures, materials: &Materials) -> Box<dyn Material> {
+        match self {
+            MaterialDescription::Lambertian { albedo } => Box::new(Lambertian::textured(albedo.build(textures))),
+            MaterialDescription::Metal { albedo, fuzz } => {
+                Box::new(Metal::textured(albedo.build(textures), fuzz.build(textures)))
+            }
+            MaterialDescription::Dielectric { index_of_refraction } => {
+                Box::new(Dielectric::textured(index_of_refraction.build(textures)))
+            }
+            MaterialDescription::DiffuseLight { emit: ColorParameter::Value(emit) } => Box::new(DiffuseLight::new(vec3(*emit))),
+            MaterialDescription::DiffuseLight { emit } => Box::new(DiffuseLight::textured(emit.build(textures))),
+            MaterialDescription::NormalMapped { material, map, strength } => {
+                let material = materials[material].get_ref().build(textures, materials);
+                Box::new(NormalMapped::new(material, Arc::clone(&textures[map])).with_strength(*strength))
+            }
+            MaterialDescription::BumpMapped { material, height, scale } => {
+                let material = materials[material].get_ref().build(textures, materials);
+                Box::new(BumpMapped::new(material, Arc::clone(&textures[height]), *scale))
+            }
+        }
+    }
+
+    /// The name of the material this one wraps, if any.
+    fn wrapped(&self) -> Option<&str> {
+        match self {
+            MaterialDescription::NormalMapped { material, .. } | MaterialDescription::BumpMapped { material, .. } => {
+                Some(material)
+            }
+            _ => None,
+        }
+    }
+
+    fn textures(&self) -> impl Iterator<Item = &str> {
+        let names = match self {
+            MaterialDescription::Lambertian { albedo } => [albedo.texture(), None],
+            MaterialDescription::Metal { albedo, fuzz } => [albedo.texture(), fuzz.texture()],
+            MaterialDescription::Dielectric { index_of_refraction } => [index_of_refraction.texture(), None],
+            MaterialDescription::DiffuseLight { emit } => [emit.texture(), None],
+            MaterialDescription::NormalMapped { map, .. } => [Some(map.as_str()), None],
+            MaterialDescription::BumpMapped { height, .. } => [Some(height.as_str()), None],
+        };
+        names.into_iter().flatten()
+    }
+
+    fn is_emissive(&self, materials: &Materials) -> bool {
+        match self.wrapped() {
+            Some(material) => materials[material].get_ref().is_emissive(materials),
+            None => matches!(self, MaterialDescription::DiffuseLight { .. }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDescription {
+    center: [f32; 3],
+    radius: f32,
+    material: Spanned<String>,
+}
+
+/// The front face is the one the vertices wind anticlockwise around.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriangleDescription {
+    vertices: [[f32; 3]; 3],
+    material: Spanned<String>,
+}
+
+/// An .obj, .gltf, .glb, .ply or .stl file, relative to the scene file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshDescription {
+    path: Spanned<String>,
+    /// Needed by PLY and STL files, which hold no materials. OBJ and glTF files
+    /// bring their own.
+    material: Option<Spanned<String>>,
+    /// Applied first, then the rotation, then the translation.
+    #[serde(default = "default_scale")]
+    scale: [f32; 3],
+    /// Degrees about the x, y and z axes, in that order.
+    #[serde(default)]
+    rotate: [f32; 3],
+    #[serde(default)]
+    translate: [f32; 3],
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+impl MeshDescription {
+    /// The placement of the mesh, or `None` if it stays where it is. The scale
+    /// factors must not be zero.
+    fn transform(&self) -> Option<Transform> {
+        if self.scale == default_scale() && self.rotate == [0.0; 3] && self.translate == [0.0; 3] {
+            return None;
+        }
+
+        let [x, y, z] = self.rotate;
+        Some(Transform::scaling(&vec3(self.scale))
+            .then(&Transform::rotation_x(x))
+            .then(&Transform::rotation_y(y))
+            .then(&Transform::rotation_z(z))
+            .then(&Transform::translation(&vec3(self.translate))))
+    }
+}
+
+/// Angles are in degrees.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightDescription {
+    Point {
+        position: [f32; 3],
+        intensity: [f32; 3],
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        intensity: [f32; 3],
+        cone_angle: f32,
+        /// Defaults to `cone_angle`, giving a hard edge.
+        falloff_angle: Option<f32>,
+    },
+    Directional {
+        direction: [f32; 3],
+        irradiance: [f32; 3],
+        #[serde(default)]
+        angular_diameter: f32,
+    },
+}
+
+impl LightDescription {
+    fn build(&self) -> Light {
+        match self {
+            LightDescription::Point { position, intensity } => Light::point(vec3(*position), vec3(*intensity)),
+            LightDescription::Spot { position, direction, intensity, cone_angle, falloff_angle } => Light::spot(
+                vec3(*position),
+                vec3(*direction),
+                vec3(*intensity),
+                *cone_angle,
+                falloff_angle.unwrap_or(*cone_angle),
+            ),
+            LightDescription::Directional { direction, irradiance, angular_diameter } => {
+                Light::directional(vec3(*direction), vec3(*irradiance), *angular_diameter)
+            }
+        }
+    }
+
+    fn direction(&self) -> Option<[f32; 3]> {
+        match self {
+            LightDescription::Point { .. } => None,
+            LightDescription::Spot { direction, .. } | LightDescription::Directional { direction, .. } => Some(*direction),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "cannot read scene file: {}", error),
+            SceneError::Parse { line, column, message } => {
+                write!(f, "line {}, column {}: {}", line, column, message)
+            }
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(error: io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+pub fn load_scene_file(path: &Path) -> Result<SceneFile, SceneError> {
+    parse_scene(&fs::read_to_string(path)?, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Parses a scene description. Files it refers to are looked up relative to
+/// `directory`.
+pub fn parse_scene(source: &str, directory: &Path) -> Result<SceneFile, SceneError> {
+    let description: SceneDescription = toml::from_str(source).map_err(|error| {
+        parse_error(source, error.span(), error.message())
+    })?;
+
+    let camera_span = description.camera.span();
+    let camera = description.camera.into_inner()
+        .build(directory)
+        .map_err(|message| parse_error(source, Some(camera_span), &message))?;
+
+    let mut textures = Textures::new();
+    for (name, texture) in in_file_order(&description.textures) {
+        let built = texture.get_ref()
+            .build(directory)
+            .map_err(|message| parse_error(source, Some(texture.span()), &message))?;
+        textures.insert(name.clone(), built);
+    }
+    for (name, material) in in_file_order(&description.materials) {
+        if let Some(texture) = material.get_ref().textures().find(|texture| !textures.contains_key(*texture)) {
+            return Err(parse_error(source, Some(material.span()), &format!("unknown texture '{}'", texture)));
+        }
+
+        // Follow the chain of wrapped materials to its end.
+        let mut chain = vec![name.as_str()];
+        let mut current = material.get_ref();
+        while let Some(wrapped) = current.wrapped() {
+            if chain.contains(&wrapped) {
+                return Err(parse_error(source, Some(material.span()), &format!("material '{}' wraps itself", name)));
+            }
+            current = description.materials.get(wrapped).map(Spanned::get_ref).ok_or_else(|| {
+                parse_error(source, Some(material.span()), &format!("unknown material '{}'", wrapped))
+            })?;
+            chain.push(wrapped);
+        }
+    }
+
+    let find_material = |name: &Spanned<String>| {
+        description.materials.get(name.get_ref()).map(Spanned::get_ref).ok_or_else(|| {
+            parse_error(source, Some(name.span()), &format!("unknown material '{}'", name.get_ref()))
+        })
+    };
+
+    let mut world = World::new();
+    let mut light_shapes = World::new();
+    for sphere in &description.spheres {
+        let material = find_material(&sphere.material)?;
+        if material.is_emissive(&description.materials) {
+            light_shapes = light_shapes.add(Box::new(Sphere::new(vec3(sphere.center), sphere.radius, material.build(&textures, &description.materials))));
+        }
+        world = world.add(Box::new(Sphere::new(vec3(sphere.center), sphere.radius, material.build(&textures, &description.materials))));
+    }
+
+    for triangle in &description.triangles {
+        let material = find_material(&triangle.material)?;
+        let [v0, v1, v2] = triangle.vertices.map(vec3);
+        if material.is_emissive(&description.materials) {
+            light_shapes = light_shapes.add(Box::new(Triangle::new(v0.clone(), v1.clone(), v2.clone(), material.build(&textures, &description.materials))));
+        }
+        world = world.add(Box::new(Triangle::new(v0, v1, v2, material.build(&textures, &description.materials))));
+    }
+
+    // Meshes loaded more than once share their geometry.
+    let mut loaded_meshes: HashMap<(&str, Option<&str>), Vec<Arc<TriangleMesh<'static>>>> = HashMap::new();
+    for mesh in &description.meshes {
+        if mesh.scale.contains(&0.0) {
+            return Err(parse_error(source, Some(mesh.path.span()), "scale factors must not be zero"));
+        }
+        let material_name = mesh.material.as_ref().map(|name| name.get_ref().as_str());
+        let key = (mesh.path.get_ref().as_str(), material_name);
+
+        let shared_meshes = match loaded_meshes.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let material = mesh.material.as_ref().map(|name| find_material(name).map(|material| (name, material))).transpose()?;
+                let meshes = load_meshes(&directory.join(mesh.path.get_ref()), material, &textures, &description.materials)
+                    .map_err(|(span, message)| parse_error(source, Some(span.unwrap_or(mesh.path.span())), &message))?;
+                entry.insert(meshes.into_iter().map(Arc::new).collect())
+            }
+        };
+
+        let transform = mesh.transform();
+        let place = |shared: &Arc<TriangleMesh<'static>>| -> Box<dyn Hittable> {
+            match &transform {
+                Some(transform) => Box::new(Transformed::new(Arc::clone(shared), transform.clone())),
+                None => Box::new(Arc::clone(shared)),
+            }
+        };
+        for shared in shared_meshes.iter() {
+            if shared.is_emissive() {
+                light_shapes = light_shapes.add(place(shared));
+            }
+            world = world.add(place(shared));
+        }
+    }
+
+    let mut lights = Lights::new(light_shapes);
+    for light in description.lights.iter() {
+        if light.get_ref().direction().is_some_and(|direction| vec3(direction).length_squared() == 0.0) {
+            return Err(parse_error(source, Some(light.span()), "light direction must not be zero"));
+        }
+        lights = lights.add(light.get_ref().build());
+    }
+
+    let add_sun = matches!(
+        description.background.as_ref().map(|background| background.get_ref()),
+        Some(BackgroundDescription::Sky { sun: true, .. })
+    );
+    let background = match description.background {
+        Some(background) => background.get_ref()
+            .build(directory)
+            .map_err(|message| parse_error(source, Some(background.span()), &message))?,
+        None => Background::default(),
+    };
+    if let (Background::Sky(sky), true) = (&background, add_sun) {
+        lights = lights.add(sky.sun());
+    }
+
+    Ok(SceneFile { camera, background, world, lights })
+}
+
+// Errors come with the span of the material name when it is to blame.
+fn load_meshes(
+    path: &Path,
+    material: Option<(&Spanned<String>, &MaterialDescription)>,
+    textures: &Textures,
+    materials: &Materials,
+) -> Result<Vec<TriangleMesh<'static>>, (Option<Range<usize>>, String)> {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match (extension.as_deref(), material) {
+        (Some("obj"), None) => load_obj(path)
+            .map(|meshes| meshes.into_iter().map(|obj| obj.mesh).collect())
+            .map_err(|error| (None, error.to_string())),
+        (Some("gltf") | Some("glb"), None) => load_gltf(path)
+            .map(|scene| scene.meshes.into_iter().map(|gltf| gltf.mesh).collect())
+            .map_err(|error| (None, error.to_string())),
+        (Some("obj") | Some("gltf") | Some("glb"), Some((name, _))) => {
+            Err((Some(name.span()), "OBJ and glTF files bring their own materials".to_string()))
+        }
+        (Some("ply"), Some((_, material))) => load_ply(path, material.build(textures, materials))
+            .map(|ply| vec![ply.mesh])
+            .map_err(|error| (None, error.to_string())),
+        (Some("stl"), Some((_, material))) => load_stl(path, material.build(textures, materials))
+            .map(|mesh| vec![mesh])
+            .map_err(|error| (None, error.to_string())),
+        (Some("ply") | Some("stl"), None) => Err((None, "PLY and STL meshes need a material".to_string())),
+        _ => Err((None, "expected an .obj, .gltf, .glb, .ply or .stl file".to_string())),
+    }
+}
+
+// Checking entries in the order they are written reports the first of
+// several errors, whatever order the map holds them in.
+fn in_file_order<T>(map: &HashMap<String, Spanned<T>>) -> Vec<(&String, &Spanned<T>)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(_, entry)| entry.span().start);
+    entries
+}
+
+fn parse_error(source: &str, span: Option<Range<usize>>, message: &str) -> SceneError {
+    let offset = span.map_or(0, |span| span.start.min(source.len()));
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rfind('\n').map_or(before.chars().count(), |newline| before[newline + 1..].chars().count()) + 1;
+
+    SceneError::Parse {
+        line,
+        column,
+        message: message.trim_end().to_string(),
+    }
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Point3::new(v[0], v[1], v[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{Point3, Vec3};
+    use crate::hittable::Hittable;
+    use crate::ray::Ray;
+    use crate::scene_file::{parse_scene, SceneError};
+    use crate::utils::fixture;
+
+    const SCENE: &str = r#"[camera]
+look_from = [0.0, 0.0, 5.0]
+look_at = [0.0, 0.0, 0.0]
+vertical_fov = 40.0
+aspect_ratio = 1.5
+
+[materials.matte]
+type = "lambertian"
+albedo = [0.5, 0.5, 0.5]
+
+[materials.lamp]
+type = "diffuse_light"
+emit = [4.0, 4.0, 4.0]
+
+[[spheres]]
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "matte"
+
+[[spheres]]
+center = [0.0, 3.0, 0.0]
+radius = 0.5
+material = "lamp"
+
+[[lights]]
+type = "point"
+position = [0.0, 5.0, 0.0]
+intensity = [1.0, 1.0, 1.0]
+"#;
+
+    // Parses the scene with `old` replaced by `new`, expecting it to fail.
+    fn error(old: &str, new: &str) -> (usize, usize, String) {
+        assert!(SCENE.contains(old));
+        match parse_scene(&SCENE.replacen(old, new, 1), Path::new("")) {
+            Err(SceneError::Parse { line, column, message }) => (line, column, message),
+            Err(error) => panic!("expected a parse error, got '{}'", error),
+            Ok(_) => panic!("expected a parse error, got none"),
+        }
+    }
+
+    #[test]
+    fn valid_scenes_load() {
+        let scene = parse_scene(SCENE, Path::new("")).unwrap();
+        assert_eq!(scene.camera.vertical_fov, 40.0);
+        assert_eq!(scene.camera.view_up, [0.0, 1.0, 0.0]);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = scene.world.hit(&ray, 0.001, f32::INFINITY);
+        assert!(rec.is_hit && (rec.t - 4.0).abs() < 1e-4);
+        assert!(!rec.material.is_emissive());
+
+        // The emissive sphere is sampled as well as the point light.
+        assert_eq!(scene.lights.shapes.into_objects().len(), 1);
+        assert_eq!(scene.lights.delta.len(), 1);
+    }
+
+    #[test]
+    fn unknown_names_are_reported_where_they_are_used() {
+        let (line, column, message) = error("material = \"matte\"", "material = \"chalk\"");
+        assert_eq!((line, column), (18, 12));
+        assert_eq!(message, "unknown material 'chalk'");
+
+        let (line, column, message) = error("albedo = [0.5, 0.5, 0.5]", "albedo = \"stripes\"");
+        assert_eq!((line, column), (7, 1));
+        assert_eq!(message, "unknown texture 'stripes'");
+    }
+
+    #[test]
+    fn missing_fields_are_reported_at_their_table() {
+        let (line, column, message) = error("radius = 1.0\n", "");
+        assert_eq!((line, column), (15, 1));
+        assert_eq!(message, "missing field `radius`");
+
+        let (line, column, message) = error("emit = [4.0, 4.0, 4.0]\n", "");
+        assert_eq!((line, column), (11, 1));
+        assert_eq!(message, "missing field `emit`");
+    }
+
+    #[test]
+    fn the_first_of_several_bad_entries_is_reported() {
+        let bad_materials = "[materials.a]\ntype = \"lambertian\"\nalbedo = \"x\"\n\n[materials.b]\ntype = \"lambertian\"\nalbedo = \"y\"\n\n[materials.matte]";
+        for _ in 0..10 {
+            let (line, _, message) = error("[materials.matte]", bad_materials);
+            assert_eq!((line, message.as_str()), (7, "unknown texture 'x'"));
+        }
+    }
+
+    #[test]
+    fn badly_typed_fields_are_reported_at_their_value() {
+        let (line, column, message) = error("radius = 1.0", "radius = \"big\"");
+        assert_eq!((line, column), (17, 10));
+        assert!(message.contains("expected f32"), "{}", message);
+
+        let (line, column, message) = error("albedo = [0.5, 0.5, 0.5]", "albedo = [0.5, 0.5]");
+        assert_eq!((line, column), (9, 10));
+        assert_eq!(message, "expected a colour [r, g, b] or the name of a texture");
+
+        let (line, column, _) = error("type = \"lambertian\"", "type = \"plastic\"");
+        assert_eq!((line, column), (8, 8));
+    }
+
+    #[test]
+    fn noise_octaves_are_bounded() {
+        let noise = |octaves: u32| format!("[textures.clouds]\ntype = \"noise\"\noctaves = {}\n\n[materials.matte]", octaves);
+        let (line, column, message) = error("[materials.matte]", &noise(40));
+        assert_eq!((line, column), (7, 1));
+        assert_eq!(message, "octaves must be between 1 and 24");
+
+        let (_, _, message) = error("[materials.matte]", &noise(0));
+        assert_eq!(message, "octaves must be between 1 and 24");
+        assert!(parse_scene(&SCENE.replacen("[materials.matte]", &noise(24), 1), Path::new("")).is_ok());
+    }
+
+    #[test]
+    fn degenerate_cameras_are_rejected() {
+        let (line, column, message) = error("look_at = [0.0, 0.0, 0.0]", "look_at = [0.0, 0.0, 5.0]");
+        assert_eq!((line, column), (1, 1));
+        assert_eq!(message, "look_from and look_at must differ");
+
+        let (line, column, message) = error("look_at = [0.0, 0.0, 0.0]", "look_at = [0.0, 0.0, 0.0]\nview_up = [0.0, 0.0, -2.0]");
+        assert_eq!((line, column), (1, 1));
+        assert_eq!(message, "view_up must not be parallel to the view direction");
+
+        let (_, _, message) = error("vertical_fov = 40.0", "vertical_fov = 0.0");
+        assert_eq!(message, "vertical_fov must be between 0 and 180 degrees");
+
+        let (_, _, message) = error("aspect_ratio = 1.5", "aspect_ratio = -1.5");
+        assert_eq!(message, "aspect_ratio must be positive");
+    }
+
+    #[test]
+    fn fields_of_other_material_types_are_rejected() {
+        let (line, column, message) = error("albedo = [0.5, 0.5, 0.5]", "albedo = [0.5, 0.5, 0.5]\nfuzz = 0.1");
+        assert_eq!((line, column), (7, 1));
+        assert_eq!(message, "field `fuzz` does not apply to this type of material");
+
+        let (line, column, message) = error("albedo = [0.5, 0.5, 0.5]", "albedo = [0.5, 0.5, 0.5]\ncolour = 1");
+        assert_eq!((line, column), (10, 1));
+        assert!(message.starts_with("unknown field `colour`"), "{}", message);
+    }
+
+    #[test]
+    fn emissive_meshes_are_sampled_as_lights() {
+        let fixtures = fixture("");
+        let meshes = "[[meshes]]\npath = \"panels.ply\"\nmaterial = \"lamp\"\n\n[[meshes]]\npath = \"panels.ply\"\nmaterial = \"matte\"\ntranslate = [5.0, 0.0, 0.0]\n\n[[lights]]";
+        let scene = parse_scene(&SCENE.replacen("[[lights]]", meshes, 1), &fixtures).unwrap();
+
+        // The lamp sphere and the first mesh.
+        assert_eq!(scene.lights.shapes.into_objects().len(), 2);
+    }
+
+    #[test]
+    fn cameras_can_come_from_gltf_files() {
+        let fixtures = fixture("");
+        let camera = "[camera]\nlook_from = [0.0, 0.0, 5.0]\nlook_at = [0.0, 0.0, 0.0]\nvertical_fov = 40.0\naspect_ratio = 1.5\n";
+        assert!(SCENE.starts_with(camera));
+
+        let scene = SCENE.replacen(camera, "camera = { gltf = \"panels.gltf\", index = 0 }\n", 1);
+        let loaded = parse_scene(&scene, &fixtures).unwrap();
+        assert_eq!(loaded.camera.look_from, [0.0, 1.0, 5.0]);
+        assert_eq!(loaded.camera.look_at, [0.0, 1.0, 4.0]);
+        assert_eq!(loaded.camera.aspect_ratio, 2.0);
+
+        let scene = SCENE.replacen(camera, "camera = { gltf = \"panels.gltf\", index = 1 }\n", 1);
+        match parse_scene(&scene, &fixtures) {
+            Err(SceneError::Parse { line, column, message }) => {
+                assert_eq!((line, column), (1, 10));
+                assert_eq!(message, "'panels.gltf' has no perspective camera at index 1");
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        let scene = SCENE.replacen(camera, "camera = { gltf = \"panels.gltf\", fov = 1 }\n", 1);
+        assert!(matches!(parse_scene(&scene, &fixtures), Err(SceneError::Parse { line: 1, .. })));
+    }
+}
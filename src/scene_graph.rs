@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::{Point3, Vec3};
+use crate::bvh::BvhNode;
+use crate::hittable::Hittable;
+use crate::hittable_list::World;
+use crate::material::Material;
+use crate::sphere::Sphere;
+use crate::transform::{Transform, Transformed};
+use crate::triangle::Triangle;
+use crate::triangle_mesh::TriangleMesh;
+
+/// What a scene node draws. All but `Object` take the material the node
+/// inherits.
+#[derive(Clone)]
+pub enum Geometry {
+    Sphere {
+        center: Point3,
+        radius: f32,
+    },
+    Triangle([Point3; 3]),
+    /// Nodes that share one mesh, with the same material, share its triangles
+    /// once flattened.
+    Mesh(Arc<MeshGeometry>),
+    /// A finished object with its own materials, which inherited ones do not
+    /// replace.
+    Object(Arc<dyn Hittable>),
+}
+
+/// The arrays of a `TriangleMesh`, without a material, which must fit
+/// together as `TriangleMesh::new` requires.
+pub struct MeshGeometry {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f32, f32)>,
+    pub indices: Vec<[usize; 3]>,
+}
+
+/// A node of a scene graph. Its transform places it relative to its parent,
+/// and its material, if it has one, is used by it and by every descendant
+/// that does not set its own.
+///
+/// Nodes are found by a path of child names separated by `/`, relative to the
+/// node searched from:
+///
+/// ```
+/// use std::sync::Arc;
+/// use ray_trace::{Color, Point3, Vec3};
+/// use ray_trace::material::Lambertian;
+/// use ray_trace::scene_graph::{Geometry, SceneNode};
+/// use ray_trace::transform::Transform;
+///
+/// # fn main() -> Result<(), ray_trace::scene_graph::SceneGraphError> {
+/// let mut scene = SceneNode::new("scene")?
+///     .with_material(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))
+///     .add(SceneNode::new("table")?
+///         .add(SceneNode::new("ball")?.with_geometry(Geometry::Sphere { center: Point3::origin(), radius: 0.5 }))?)?;
+///
+/// let ball = scene.find_mut("table/ball").unwrap();
+/// ball.set_transform(Transform::translation(&Vec3::new(0.0, 1.0, 0.0)));
+/// assert!(scene.flatten().is_ok());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SceneNode {
+    name: String,
+    transform: Transform,
+    material: Option<Arc<dyn Material>>,
+    geometry: Option<Geometry>,
+    children: Vec<SceneNode>,
+}
+
+#[derive(Debug)]
+pub enum SceneGraphError {
+    /// A node name containing `/`.
+    InvalidName(String),
+    DuplicateChild {
+        parent: String,
+        child: String,
+    },
+    /// The path of a node that has geometry but no material to use.
+    MissingMaterial(String),
+    /// The path of a node whose mesh arrays do not fit together.
+    InvalidMesh(String),
+}
+
+impl Display for SceneGraphError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneGraphError::InvalidName(name) => write!(f, "node name '{}' cannot contain '/'", name),
+            SceneGraphError::DuplicateChild { parent, child } => {
+                write!(f, "'{}' already has a child named '{}'", parent, child)
+            }
+            SceneGraphError::MissingMaterial(path) => write!(f, "'{}' has no material", path),
+            SceneGraphError::InvalidMesh(path) => write!(f, "'{}' has a mesh with mismatched arrays", path),
+        }
+    }
+}
+
+impl Error for SceneGraphError {}
+
+/// A scene graph flattened for rendering.
+pub struct FlattenedScene {
+    pub world: BvhNode<Box<dyn Hittable>>,
+    /// Every emissive sphere, triangle and mesh, for sampling them directly.
+    pub lights: World,
+}
+
+impl SceneNode {
+    /// Names may not contain `/`, which separates them in paths.
+    pub fn new(name: &str) -> Result<SceneNode, SceneGraphError> {
+        if name.contains('/') {
+            return Err(SceneGraphError::InvalidName(name.to_string()));
+        }
+        Ok(SceneNode {
+            name: name.to_string(),
+            transform: Transform::identity(),
+            material: None,
+            geometry: None,
+            children: vec![],
+        })
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> SceneNode {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_material(mut self, material: Arc<dyn Material>) -> SceneNode {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn with_geometry(mut self, geometry: Geometry) -> SceneNode {
+        self.geometry = Some(geometry);
+        self
+    }
+
+    /// Adds a child, whose name must differ from those of its siblings.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, child: SceneNode) -> Result<SceneNode, SceneGraphError> {
+        if self.child(&child.name).is_some() {
+            return Err(SceneGraphError::DuplicateChild { parent: self.name, child: child.name });
+        }
+        self.children.push(child);
+        Ok(self)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn material(&self) -> Option<&Arc<dyn Material>> {
+        self.material.as_ref()
+    }
+
+    /// `None` makes the node inherit its parent's material again.
+    pub fn set_material(&mut self, material: Option<Arc<dyn Material>>) {
+        self.material = material;
+    }
+
+    pub fn geometry(&self) -> Option<&Geometry> {
+        self.geometry.as_ref()
+    }
+
+    pub fn set_geometry(&mut self, geometry: Option<Geometry>) {
+        self.geometry = geometry;
+    }
+
+    pub fn children(&self) -> &[SceneNode] {
+        &self.children
+    }
+
+    pub fn child(&self, name: &str) -> Option<&SceneNode> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Finds a descendant by its path. The empty path is the node itself.
+    pub fn find(&self, path: &str) -> Option<&SceneNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| node.child(name))
+    }
+
+    pub fn find_mut(&mut self, path: &str) -> Option<&mut SceneNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| node.children.iter_mut().find(|child| child.name == name))
+    }
+
+    /// Builds a BVH over every object below this node, with all transforms
+    /// applied, along with the lights to sample: the emissive spheres,
+    /// triangles and meshes, but not objects. Fails with the path of the
+    /// first node that has geometry but no material to use, or a mesh whose
+    /// arrays do not fit together.
+    pub fn flatten(&self) -> Result<FlattenedScene, SceneGraphError> {
+        let mut flattener = Flattener { objects: vec![], lights: vec![], meshes: HashMap::new() };
+        flattener.visit(self, &Transform::identity(), None, &self.name)?;
+
+        let objects = flattener.objects.into_iter().fold(World::new(), |world, object| world.add(object));
+        let lights = flattener.lights.into_iter().fold(World::new(), |lights, light| lights.add(light));
+        Ok(FlattenedScene { world: BvhNode::new(objects), lights })
+    }
+}
+
+// A shared mesh is keyed by its geometry and material.
+type MeshKey = (*const MeshGeometry, *const ());
+
+struct Flattener {
+    objects: Vec<Box<dyn Hittable>>,
+    lights: Vec<Box<dyn Hittable>>,
+    // The keys stay valid while the graph being flattened is borrowed.
+    meshes: HashMap<MeshKey, Arc<TriangleMesh<'static>>>,
+}
+
+impl Flattener {
+    fn visit(
+        &mut self,
+        node: &SceneNode,
+        parent_transform: &Transform,
+        inherited: Option<&Arc<dyn Material>>,
+        path: &str,
+    ) -> Result<(), SceneGraphError> {
+        let transform = node.transform.clone().then(parent_transform);
+        let material = node.material.as_ref().or(inherited);
+
+        if let Some(geometry) = &node.geometry {
+            self.add(geometry, &transform, material, path)?;
+        }
+
+        for child in &node.children {
+            self.visit(child, &transform, material, &format!("{}/{}", path, child.name))?;
+        }
+        Ok(())
+    }
+
+    fn add(
+        &mut self,
+        geometry: &Geometry,
+        transform: &Transform,
+        material: Option<&Arc<dyn Material>>,
+        path: &str,
+    ) -> Result<(), SceneGraphError> {
+        // Objects bring their own materials; everything else needs the node's.
+        let material = || material.ok_or_else(|| SceneGraphError::MissingMaterial(path.to_string()));
+
+        match geometry {
+            Geometry::Sphere { center, radius } => {
+                let material = material()?;
+                let sphere = || Sphere::new(center.clone(), *radius, Box::new(Arc::clone(material)));
+                self.objects.push(place(sphere(), transform));
+                if material.is_emissive() {
+                    self.lights.push(place(sphere(), transform));
+                }
+            }
+            // Triangles are cheap to move, so their vertices are transformed.
+            Geometry::Triangle(vertices) => {
+                let material = material()?;
+                let triangle = || {
+                    let [v0, v1, v2] = vertices.clone().map(|vertex| transform.point(&vertex));
+                    Box::new(Triangle::new(v0, v1, v2, Box::new(Arc::clone(material))))
+                };
+                self.objects.push(triangle());
+                if material.is_emissive() {
+                    self.lights.push(triangle());
+                }
+            }
+            Geometry::Mesh(mesh) => {
+                let material = material()?;
+                let key = (Arc::as_ptr(mesh), Arc::as_ptr(material) as *const ());
+                let shared = match self.meshes.entry(key) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let built = TriangleMesh::new(
+                            mesh.positions.clone(),
+                            mesh.normals.clone(),
+                            mesh.uvs.clone(),
+                            mesh.indices.clone(),
+                            Box::new(Arc::clone(material)),
+                        )
+                        .map_err(|_| SceneGraphError::InvalidMesh(path.to_string()))?;
+                        entry.insert(Arc::new(built))
+                    }
+                };
+                self.objects.push(place(Arc::clone(shared), transform));
+                if material.is_emissive() {
+                    self.lights.push(place(Arc::clone(shared), transform));
+                }
+            }
+            Geometry::Object(object) => self.objects.push(place(Arc::clone(object), transform)),
+        }
+        Ok(())
+    }
+}
+
+fn place<H: Hittable + 'static>(object: H, transform: &Transform) -> Box<dyn Hittable> {
+    if *transform == Transform::identity() {
+        Box::new(object)
+    } else {
+        Box::new(Transformed::new(object, transform.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Point3, Vec3};
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material::{DiffuseLight, Lambertian};
+    use crate::ray::Ray;
+    use crate::scene_graph::{Geometry, MeshGeometry, SceneGraphError, SceneNode};
+    use crate::transform::Transform;
+
+    fn ball() -> Geometry {
+        Geometry::Sphere { center: Point3::origin(), radius: 0.5 }
+    }
+
+    fn node(name: &str) -> SceneNode {
+        SceneNode::new(name).unwrap()
+    }
+
+    fn scene() -> SceneNode {
+        build_scene().unwrap()
+    }
+
+    fn build_scene() -> Result<SceneNode, SceneGraphError> {
+        SceneNode::new("scene")?
+            .with_material(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))
+            .add(SceneNode::new("table")?
+                .with_transform(Transform::translation(&Vec3::new(0.0, 1.0, 0.0)))
+                .add(SceneNode::new("ball")?
+                    .with_transform(Transform::translation(&Vec3::new(2.0, 0.0, 0.0)))
+                    .with_geometry(ball()))?
+                .add(SceneNode::new("lamp")?
+                    .with_material(Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0))))
+                    .with_geometry(ball()))?)
+    }
+
+    fn hit_material_is_emissive(scene: &SceneNode, x: f32, y: f32) -> Option<bool> {
+        let flattened = scene.flatten().unwrap();
+        let rec = flattened.world.hit(&Ray::new(Point3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+        rec.is_hit.then(|| rec.material.is_emissive())
+    }
+
+    #[test]
+    fn nodes_are_found_by_path() {
+        let mut scene = scene();
+        assert_eq!(scene.find("table/ball").unwrap().name(), "ball");
+        assert_eq!(scene.find("/table/lamp/").unwrap().name(), "lamp");
+        assert_eq!(scene.find("").unwrap().name(), "scene");
+        assert!(scene.find("table/chair").is_none());
+        assert!(scene.find("ball").is_none());
+
+        scene.find_mut("table/ball").unwrap().set_geometry(None);
+        assert!(scene.find("table/ball").unwrap().geometry().is_none());
+    }
+
+    #[test]
+    fn transforms_compose_and_materials_are_inherited() {
+        let mut scene = scene();
+        assert_eq!(hit_material_is_emissive(&scene, 2.0, 1.0), Some(false));
+        assert_eq!(hit_material_is_emissive(&scene, 0.0, 1.0), Some(true));
+        assert_eq!(hit_material_is_emissive(&scene, 0.0, 0.0), None);
+
+        let flattened = scene.flatten().unwrap();
+        assert!(flattened.lights.pdf_value(&Point3::new(0.0, 1.0, 5.0), &Vec3::new(0.0, 0.0, -1.0)) > 0.0);
+        assert_eq!(flattened.lights.pdf_value(&Point3::new(2.0, 1.0, 5.0), &Vec3::new(0.0, 0.0, -1.0)), 0.0);
+
+        // Moving the table moves both objects; the ball can take the lamp's material.
+        let table = scene.find_mut("table").unwrap();
+        table.set_transform(Transform::translation(&Vec3::new(0.0, -1.0, 0.0)));
+        let lamp_material = table.find("lamp").unwrap().material().cloned();
+        table.find_mut("ball").unwrap().set_material(lamp_material);
+        assert_eq!(hit_material_is_emissive(&scene, 2.0, -1.0), Some(true));
+        assert_eq!(hit_material_is_emissive(&scene, 0.0, -1.0), Some(true));
+    }
+
+    #[test]
+    fn geometry_without_a_material_is_reported_by_path() {
+        let group = node("group").add(node("orphan").with_geometry(ball())).unwrap();
+        let scene = node("scene").add(group).unwrap();
+        assert!(matches!(
+            scene.flatten().err().unwrap(),
+            SceneGraphError::MissingMaterial(path) if path == "scene/group/orphan"
+        ));
+    }
+
+    #[test]
+    fn emissive_meshes_are_sampled_as_lights() {
+        let positions = vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+        let geometry = Geometry::Mesh(Arc::new(MeshGeometry { positions, normals: vec![], uvs: vec![], indices: vec![[0, 1, 2]] }));
+        let scene = node("scene")
+            .add(node("lamp")
+                .with_material(Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0))))
+                .with_transform(Transform::translation(&Vec3::new(0.0, 2.0, 0.0)))
+                .with_geometry(geometry.clone()))
+            .and_then(|scene| scene.add(node("shade")
+                .with_material(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))
+                .with_geometry(geometry)))
+            .unwrap();
+
+        let flattened = scene.flatten().unwrap();
+        let origin = Point3::new(0.25, 0.25, 5.0);
+        assert!(flattened.lights.pdf_value(&origin, &Vec3::new(0.0, 2.0, -5.0)) > 0.0);
+        assert_eq!(flattened.lights.pdf_value(&origin, &Vec3::new(0.0, 0.0, -5.0)), 0.0);
+    }
+
+    #[test]
+    fn meshes_with_mismatched_arrays_are_reported_by_path() {
+        let mesh = |normals: Vec<Vec3>, indices: Vec<[usize; 3]>| {
+            let positions = vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+            let geometry = Geometry::Mesh(Arc::new(MeshGeometry { positions, normals, uvs: vec![], indices }));
+            let scene = node("scene")
+                .with_material(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))
+                .add(node("mesh").with_geometry(geometry))
+                .unwrap();
+            scene.flatten().err()
+        };
+
+        assert!(mesh(vec![], vec![[0, 1, 2]]).is_none());
+        assert!(matches!(
+            mesh(vec![Vec3::new(0.0, 0.0, 1.0)], vec![[0, 1, 2]]),
+            Some(SceneGraphError::InvalidMesh(path)) if path == "scene/mesh"
+        ));
+        assert!(matches!(mesh(vec![], vec![[0, 1, 3]]), Some(SceneGraphError::InvalidMesh(_))));
+    }
+
+    #[test]
+    fn names_with_slashes_and_repeated_siblings_are_errors() {
+        assert!(matches!(SceneNode::new("table/ball"), Err(SceneGraphError::InvalidName(name)) if name == "table/ball"));
+
+        let error = node("table").add(node("ball")).and_then(|table| table.add(node("ball"))).err().unwrap();
+        assert_eq!(error.to_string(), "'table' already has a child named 'ball'");
+
+        // Cousins may share a name.
+        let left = node("left").add(node("ball")).unwrap();
+        let right = node("right").add(node("ball")).unwrap();
+        let scene = node("scene").add(left).and_then(|scene| scene.add(right)).unwrap();
+        assert!(scene.find("left/ball").is_some() && scene.find("right/ball").is_some());
+    }
+}
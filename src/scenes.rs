@@ -0,0 +1,224 @@
+use crate::{Point3, Vec3};
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hittable_list::World;
+use crate::material::{Dielectric, Lambertian, Metal};
+use crate::renderer::{ImageProperties, RenderProperties};
+use crate::sphere::Sphere;
+use crate::utils::{random_float, random_float_range, seed_rng};
+
+/// The scenes that are built into the renderer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuiltinScene {
+    Sample,
+    Final,
+}
+
+impl BuiltinScene {
+    pub fn from_name(name: &str) -> Option<BuiltinScene> {
+        match name {
+            "sample" => Some(BuiltinScene::Sample),
+            "final" => Some(BuiltinScene::Final),
+            _ => None,
+        }
+    }
+
+    pub fn aspect_ratio(self) -> f32 {
+        match self {
+            BuiltinScene::Sample => 16.0 / 9.0,
+            BuiltinScene::Final => 3.0 / 2.0,
+        }
+    }
+
+    pub fn image_width(self) -> i32 {
+        match self {
+            BuiltinScene::Sample => 400,
+            BuiltinScene::Final => 1200,
+        }
+    }
+
+    /// Builds the camera and world of the scene. The world is generated from `seed`.
+    pub fn setup(self, aspect_ratio: f32, seed: u64) -> (Camera, World) {
+        seed_rng(seed);
+        match self {
+            BuiltinScene::Sample => (setup_sample_camera(aspect_ratio), setup_sample_world()),
+            BuiltinScene::Final => (setup_final_camera(aspect_ratio), setup_final_world()),
+        }
+    }
+}
+
+pub fn setup_sample_scene(seed: u64) -> (ImageProperties, RenderProperties, Camera, World) {
+    let aspect_ratio = BuiltinScene::Sample.aspect_ratio();
+    let image_width = BuiltinScene::Sample.image_width();
+    let image_height: i32 = (image_width as f32 / aspect_ratio) as i32;
+    let (camera, world) = BuiltinScene::Sample.setup(aspect_ratio, seed);
+    (
+        ImageProperties {
+            image_height,
+            image_width,
+        },
+        RenderProperties {
+            samples_per_pixel: 500,
+            max_depth: 50,
+            seed,
+            thread_count: None,
+        },
+        camera,
+        world,
+    )
+}
+
+pub fn setup_sample_camera(aspect_ratio: f32) -> Camera {
+    let look_from = Point3::new(3.0, 3.0, 2.0);
+    let look_at = Point3::new(0.0, 0.0, -1.0);
+    let vertical_up = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = (&look_from - &look_at).length();
+    let aperture = 2.0;
+
+    Camera::new(
+        look_from,
+        look_at,
+        vertical_up,
+        20.0,
+        aspect_ratio,
+        aperture,
+        dist_to_focus,
+    )
+}
+
+pub fn setup_sample_world() -> World {
+    let material_ground = Lambertian::new(Color::new(0.8, 0.8, 0.0));
+    let material_center = Lambertian::new(Color::new(0.1, 0.2, 0.5));
+    let material_left = Dielectric::new(1.5);
+    let material_right = Metal::new(Color::new(0.8, 0.6, 0.2), 0.0);
+
+    World::new()
+        .add(Box::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            Box::new(material_ground),
+        )))
+        .add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Box::new(material_center),
+        )))
+        .add(Box::new(Sphere::new(
+            Point3::new(-1.0, 0.0, -1.0),
+            0.5,
+            Box::new(material_left),
+        )))
+        .add(Box::new(Sphere::new(
+            Point3::new(-1.0, 0.0, -1.0),
+            -0.4,
+            Box::new(Dielectric::new(1.5)),
+        )))
+        .add(Box::new(Sphere::new(
+            Point3::new(1.0, 0.0, -1.0),
+            0.5,
+            Box::new(material_right),
+        )))
+}
+
+pub fn setup_final_scene(seed: u64) -> (ImageProperties, RenderProperties, Camera, World) {
+    let aspect_ratio = BuiltinScene::Final.aspect_ratio();
+    let image_width = BuiltinScene::Final.image_width();
+    let image_height: i32 = (image_width as f32 / aspect_ratio) as i32;
+    let (camera, world) = BuiltinScene::Final.setup(aspect_ratio, seed);
+    (
+        ImageProperties {
+            image_height,
+            image_width,
+        },
+        RenderProperties {
+            samples_per_pixel: 500,
+            max_depth: 50,
+            seed,
+            thread_count: None,
+        },
+        camera,
+        world,
+    )
+}
+
+pub fn setup_final_camera(aspect_ratio: f32) -> Camera {
+    let look_from = Point3::new(12.0, 2.0, 3.0);
+    let look_at = Point3::new(0.0, 0.0, 0.0);
+    let vertical_up = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.1;
+
+    Camera::new(
+        look_from,
+        look_at,
+        vertical_up,
+        20.0,
+        aspect_ratio,
+        aperture,
+        dist_to_focus,
+    )
+}
+
+pub fn setup_final_world() -> World {
+    let ground_material = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+    let ground = Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(ground_material),
+    );
+
+    let material_1 = Dielectric::new(1.5);
+    let object_1 = Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(material_1),
+    );
+
+    let material_2 = Lambertian::new(Color::new(0.4, 0.2, 0.1));
+    let object_2 = Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(material_2),
+    );
+
+    let material_3 = Metal::new(Color::new(0.7, 0.6, 0.5), 0.0);
+    let object_3 = Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(material_3),
+    );
+
+    let mut world = World::new()
+        .add(Box::new(ground))
+        .add(Box::new(object_1))
+        .add(Box::new(object_2))
+        .add(Box::new(object_3));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_material = random_float();
+            let center = Point3::new(a as f32 + 0.9 * random_float(), 0.2, b as f32 + 0.9 * random_float());
+
+            if (&center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                if choose_material < 0.8 {
+                    // Diffuse
+                    let albedo = Color::random() * Color::random();
+                    let sphere_material = Lambertian::new(albedo);
+                    world = world.add(Box::new(Sphere::new(center, 0.2, Box::new(sphere_material))));
+                } else if choose_material < 0.95 {
+                    // Metal
+                    let albedo = Color::random_range(0.5, 1.0);
+                    let fuzz = random_float_range(0.0, 0.5);
+                    let sphere_material = Metal::new(albedo, fuzz);
+                    world = world.add(Box::new(Sphere::new(center, 0.2, Box::new(sphere_material))));
+                } else {
+                    // Glass
+                    let sphere_material = Dielectric::new(1.5);
+                    world = world.add(Box::new(Sphere::new(center, 0.2, Box::new(sphere_material))));
+                }
+            }
+        }
+    }
+
+    world
+}
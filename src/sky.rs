@@ -0,0 +1,208 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::Vec3;
+use crate::color::Color;
+use crate::light::Light;
+use crate::utils::{clamp, degrees_to_radians};
+
+/// Brings the model's luminance, in kcd/m², into the range of the rest of the
+/// renderer: a white surface lit by a clear sky and sun comes out close to one.
+const SKY_SCALE: f32 = 0.025;
+
+/// Illuminance of the sun above the atmosphere, in klx.
+const SOLAR_ILLUMINANCE: f32 = 127.0;
+
+/// The apparent size of the sun, in degrees.
+pub const SUN_ANGULAR_DIAMETER: f32 = 0.53;
+
+/// The analytic daylight sky of Preetham, Shirley and Smits, "A Practical
+/// Analytic Model for Daylight" (1999).
+///
+/// The sky itself does not include the sun; `sun` returns a matching light.
+pub struct Sky {
+    sun_direction: Vec3,
+    turbidity: f32,
+    intensity: f32,
+    // Zenith values and Perez coefficients of luminance and both chromaticities.
+    zenith: [f32; 3],
+    perez: [[f32; 5]; 3],
+}
+
+impl Sky {
+    /// `elevation` is the sun's angle above the horizon and `azimuth` its angle
+    /// about the vertical axis, with 0 towards -z and 90 towards +x, both in
+    /// degrees. `turbidity` runs from about 2 for a clear sky to 10 for haze.
+    pub fn new(elevation: f32, azimuth: f32, turbidity: f32, intensity: f32) -> Sky {
+        let elevation = degrees_to_radians(clamp(elevation, 0.0, 90.0));
+        let azimuth = degrees_to_radians(azimuth);
+        let t = clamp(turbidity, 1.7, 10.0);
+
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+
+        let theta_s = FRAC_PI_2 - elevation;
+        let (theta2, theta3) = (theta_s * theta_s, theta_s * theta_s * theta_s);
+        let t2 = t * t;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = t2 * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta_s)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta_s + 0.25886);
+        let zenith_y = t2 * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta_s)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta_s + 0.26688);
+
+        let perez = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        // Normalise each zenith value by the Perez function at the zenith.
+        let mut zenith = [zenith_luminance.max(0.0), zenith_x, zenith_y];
+        for (value, coefficients) in zenith.iter_mut().zip(perez.iter()) {
+            *value /= perez_function(coefficients, 1.0, theta_s);
+        }
+
+        Sky { sun_direction, turbidity: t, intensity, zenith, perez }
+    }
+
+    /// The sky's radiance seen looking along `direction`. The model only covers
+    /// the upper hemisphere; below it, the horizon continues.
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        let direction = direction.unit_vector();
+        let cos_theta = direction.y().max(0.001);
+        let gamma = clamp(direction.dot(&self.sun_direction), -1.0, 1.0).acos();
+
+        let luminance = self.zenith[0] * perez_function(&self.perez[0], cos_theta, gamma);
+        let x = self.zenith[1] * perez_function(&self.perez[1], cos_theta, gamma);
+        let y = self.zenith[2] * perez_function(&self.perez[2], cos_theta, gamma);
+
+        xyy_to_rgb(x, y, luminance) * (SKY_SCALE * self.intensity)
+    }
+
+    /// The sun seen through this sky, reddened by the air it passes through.
+    pub fn sun(&self) -> Light {
+        let elevation = self.sun_direction.y().asin();
+        let zenith_degrees = 90.0 - elevation.to_degrees();
+
+        // Kasten's relative optical air mass.
+        let air_mass = 1.0 / (elevation.sin() + 0.15 * (93.885 - zenith_degrees).powf(-1.253));
+
+        // Rayleigh and aerosol transmittance at representative red, green and
+        // blue wavelengths, in micrometres.
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |wavelength: f32| {
+            let rayleigh = (-0.008735 * wavelength.powf(-4.08) * air_mass).exp();
+            let aerosol = (-beta * wavelength.powf(-1.3) * air_mass).exp();
+            rayleigh * aerosol
+        };
+
+        let irradiance = Color::new(transmittance(0.680), transmittance(0.550), transmittance(0.440))
+            * (SOLAR_ILLUMINANCE * SKY_SCALE * self.intensity);
+        Light::directional(-&self.sun_direction, irradiance, SUN_ANGULAR_DIAMETER)
+    }
+}
+
+fn perez_function(coefficients: &[f32; 5], cos_theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = *coefficients;
+    let cos_gamma = gamma.cos();
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Color {
+    if y <= 0.0 {
+        return Color::origin();
+    }
+
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+
+    // CIE XYZ to linear sRGB.
+    Color::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Vec3;
+    use crate::color::Color;
+    use crate::light::Light;
+    use crate::sky::Sky;
+    use crate::utils::degrees_to_radians;
+
+    fn sun_irradiance(sky: &Sky) -> Color {
+        match sky.sun() {
+            Light::Directional { irradiance, .. } => irradiance,
+            _ => panic!("the sun is a directional light"),
+        }
+    }
+
+    #[test]
+    fn sun_direction_follows_elevation_and_azimuth() {
+        for (elevation, azimuth, expected) in [
+            (90.0, 0.0, Vec3::new(0.0, 1.0, 0.0)),
+            (30.0, 0.0, Vec3::new(0.0, 0.5, -(3.0f32.sqrt()) / 2.0)),
+            (30.0, 90.0, Vec3::new(3.0f32.sqrt() / 2.0, 0.5, 0.0)),
+            (45.0, 180.0, Vec3::new(0.0, 0.5f32.sqrt(), 0.5f32.sqrt())),
+        ] {
+            let sky = Sky::new(elevation, azimuth, 3.0, 1.0);
+            assert!((&sky.sun_direction - &expected).length() < 1e-5);
+            match sky.sun() {
+                // The light travels away from the sun.
+                Light::Directional { direction, .. } => assert!((&direction + &expected).length() < 1e-5),
+                _ => panic!("the sun is a directional light"),
+            }
+        }
+    }
+
+    #[test]
+    fn radiance_is_finite_and_non_negative_over_the_sphere() {
+        for turbidity in [2.0, 5.0, 10.0] {
+            for elevation in [0.0, 5.0, 30.0, 90.0] {
+                let sky = Sky::new(elevation, 40.0, turbidity, 1.0);
+                for i in 0..=18 {
+                    for j in 0..36 {
+                        let theta = degrees_to_radians(i as f32 * 10.0);
+                        let phi = degrees_to_radians(j as f32 * 10.0);
+                        let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                        let radiance = sky.radiance(&direction);
+                        for channel in [radiance.x(), radiance.y(), radiance.z()] {
+                            assert!(channel.is_finite() && channel >= 0.0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // The model stops at the horizon, and below it the horizon's colour carries
+    // on rather than going black.
+    #[test]
+    fn the_horizon_continues_below_it() {
+        let sky = Sky::new(20.0, 0.0, 3.0, 1.0);
+        for direction in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(-1.0, 0.0, -0.3)] {
+            let horizon = sky.radiance(&direction);
+            let below = sky.radiance(&(&direction + &Vec3::new(0.0, -0.5, 0.0)));
+            assert!(!below.near_zero());
+            assert!((&below - &horizon).length() < 0.5 * horizon.length());
+        }
+    }
+
+    #[test]
+    fn the_sun_reddens_towards_the_horizon() {
+        let high = sun_irradiance(&Sky::new(60.0, 0.0, 3.0, 1.0));
+        let low = sun_irradiance(&Sky::new(3.0, 0.0, 3.0, 1.0));
+
+        assert!(low.z() < high.z() && low.y() < high.y());
+        assert!(low.x() / low.z() > high.x() / high.z());
+        assert!(high.x() > high.z());
+    }
+}
@@ -0,0 +1,125 @@
+use std::f32::consts::PI;
+use std::ops::Deref;
+
+use crate::{Point3, Ray, Vec3};
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+
+pub struct Sphere<'a> {
+    center: Point3,
+    radius: f32,
+    material: Box<dyn Material + 'a>,
+}
+
+impl<'a> Sphere<'_> {
+    pub fn new(center: Point3, radius: f32, material: Box<dyn Material + 'a>) -> Sphere<'a> {
+        Sphere { center, radius, material }
+    }
+}
+
+impl Hittable for Sphere<'_> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
+        let oc = &ray.orig - &self.center;
+        let a = ray.dir.length_squared();
+        let half_b = oc.dot(&ray.dir);
+        let c = oc.length_squared() - self.radius * self.radius;
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return HitRecord::miss();
+        }
+
+        let sqrtd = discriminant.sqrt();
+
+        // Find the nearest root that lies in the acceptable range.
+        let mut root = (-half_b - sqrtd) / a;
+        if root < t_min || t_max < root {
+            root = (-half_b + sqrtd) / a;
+            if root < t_min || t_max < root {
+                return HitRecord::miss();
+            }
+        }
+
+        let t = root;
+        let p = ray.at(t);
+        let outward_normal = (&p - &self.center) / self.radius;
+
+        let (u, v) = sphere_uv(&outward_normal);
+        let (dpdu, dpdv) = sphere_tangents(&outward_normal, self.radius);
+        let (front_face, normal) = HitRecord::get_face_normal(ray, outward_normal);
+
+        HitRecord {
+            t,
+            u,
+            v,
+            dpdu,
+            dpdv,
+            vertex_color: None,
+            p,
+            material: self.material.deref(),
+            front_face,
+            normal,
+            is_hit: true,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = self.radius.abs();
+        Aabb::new(
+            &self.center - Vec3::new(r, r, r),
+            &self.center + Vec3::new(r, r, r),
+        )
+    }
+
+    // Directions are sampled uniformly over the cone the sphere subtends as seen
+    // from `origin`.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f32 {
+        let rec = self.hit(&Ray::new(origin.clone(), direction.clone()), 0.001, f32::INFINITY);
+        if !rec.is_hit {
+            return 0.0;
+        }
+
+        let distance_squared = (&self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 0.0;
+        }
+
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    fn random_direction(&self, origin: &Point3) -> Vec3 {
+        let direction = &self.center - origin;
+        let distance_squared = direction.length_squared();
+        let uvw = Onb::build_from_w(&direction);
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).max(0.0).sqrt();
+        uvw.local(&Vec3::random_in_cone(cos_theta_max))
+    }
+}
+
+// Maps a point on the unit sphere to (u, v), with u running around the y axis
+// starting from -x and v from the bottom pole to the top.
+fn sphere_uv(p: &Point3) -> (f32, f32) {
+    let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+// The derivatives of center + radius * p with respect to the (u, v) of
+// `sphere_uv`, where p = (-sin theta cos phi, -cos theta, sin theta sin phi).
+// Both vanish at the poles.
+fn sphere_tangents(p: &Point3, radius: f32) -> (Vec3, Vec3) {
+    let dpdu = Vec3::new(p.z(), 0.0, -p.x()) * (2.0 * PI * radius);
+
+    let sin_theta = (1.0 - p.y() * p.y()).max(0.0).sqrt();
+    if sin_theta < 1e-6 {
+        return (dpdu, Vec3::origin());
+    }
+    let dpdv = Vec3::new(-p.y() * p.x(), sin_theta * sin_theta, -p.y() * p.z()) * (PI * radius / sin_theta);
+    (dpdu, dpdv)
+}
+
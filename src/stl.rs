@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::Point3;
+use crate::material::Material;
+use crate::triangle_mesh::TriangleMesh;
+
+// An 80 byte header and a 32-bit triangle count precede the triangles.
+const BINARY_HEADER_SIZE: usize = 84;
+// A normal, three vertices and a two byte attribute field.
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+#[derive(Debug)]
+pub enum StlError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl Display for StlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io { path, error } => write!(f, "cannot read '{}': {}", path.display(), error),
+            StlError::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl Error for StlError {}
+
+/// Loads an ASCII or binary STL file as a flat shaded mesh of the given
+/// material. Vertices shared between facets are merged. The stored facet
+/// normals are ignored, as the vertex order already gives each facet's front.
+pub fn load_stl(path: &Path, material: Box<dyn Material>) -> Result<TriangleMesh<'static>, StlError> {
+    let data = fs::read(path).map_err(|error| StlError::Io { path: path.to_path_buf(), error })?;
+    parse_stl(&data, path, material)
+}
+
+/// Parses the contents of the STL file at `path`.
+pub fn parse_stl(data: &[u8], path: &Path, material: Box<dyn Material>) -> Result<TriangleMesh<'static>, StlError> {
+    let parse_error = |message: String| StlError::Parse { path: path.to_path_buf(), message };
+
+    // Binary files may also begin with "solid", so their size decides.
+    let facets = if is_binary(data) {
+        read_binary(data)
+    } else if data.trim_ascii_start().starts_with(b"solid") {
+        let text = std::str::from_utf8(data).map_err(|_| parse_error("ASCII file is not text".to_string()))?;
+        read_ascii(text)
+    } else {
+        Err("not an STL file".to_string())
+    }.map_err(parse_error)?;
+
+    if facets.is_empty() {
+        return Err(parse_error("the file contains no facets".to_string()));
+    }
+
+    let mut positions: Vec<Point3> = vec![];
+    // Keyed by bit pattern; adding zero turns -0 into +0 first.
+    let mut indices: HashMap<[u32; 3], usize> = HashMap::new();
+    let triangles = facets.iter()
+        .map(|facet| facet.map(|vertex| {
+            let key = vertex.map(|coordinate| (coordinate + 0.0).to_bits());
+            *indices.entry(key).or_insert_with(|| {
+                positions.push(Point3::new(vertex[0], vertex[1], vertex[2]));
+                positions.len() - 1
+            })
+        }))
+        .collect();
+
+    TriangleMesh::new(positions, vec![], vec![], triangles, material).map_err(|error| parse_error(error.to_string()))
+}
+
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() < BINARY_HEADER_SIZE {
+        return false;
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    data.len() == BINARY_HEADER_SIZE + count * BINARY_TRIANGLE_SIZE
+}
+
+fn read_binary(data: &[u8]) -> Result<Vec<[[f32; 3]; 3]>, String> {
+    let float = |bytes: &[u8], index: usize| {
+        let offset = index * 4;
+        f32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+    };
+
+    Ok(data[BINARY_HEADER_SIZE..].chunks_exact(BINARY_TRIANGLE_SIZE)
+        .map(|triangle| {
+            // Skip the normal in the first three floats.
+            [0, 1, 2].map(|vertex| [0, 1, 2].map(|axis| float(triangle, 3 + vertex * 3 + axis)))
+        })
+        .collect())
+}
+
+fn read_ascii(text: &str) -> Result<Vec<[[f32; 3]; 3]>, String> {
+    let mut facets = vec![];
+    let mut vertices: Vec<[f32; 3]> = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("vertex") => {
+                let coordinates: Vec<f32> = fields
+                    .map(|field| field.parse().map_err(|_| format!("line {}: invalid number '{}'", line_number, field)))
+                    .collect::<Result<_, _>>()?;
+                match coordinates.as_slice() {
+                    [x, y, z] => vertices.push([*x, *y, *z]),
+                    _ => return Err(format!("line {}: expected three coordinates", line_number)),
+                }
+            }
+            // Facets should be triangles, but polygons are split into fans.
+            Some("endloop") => {
+                if vertices.len() < 3 {
+                    return Err(format!("line {}: facet has fewer than three vertices", line_number));
+                }
+                for k in 1..vertices.len() - 1 {
+                    facets.push([vertices[0], vertices[k], vertices[k + 1]]);
+                }
+                vertices.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(facets)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Point3, Vec3};
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::stl::load_stl;
+    use crate::utils::fixture;
+
+    #[test]
+    fn ascii_and_binary_files_load_the_same_outward_facing_mesh() {
+        for name in ["tetrahedron.stl", "tetrahedron_binary.stl"] {
+            let mesh = load_stl(&fixture(name), Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))).unwrap();
+            assert_eq!(mesh.triangle_count(), 4, "{}", name);
+
+            let rec = mesh.hit(&Ray::new(Point3::new(1.0, 1.0, 1.0), Vec3::new(-1.0, -1.0, -1.0)), 0.001, f32::INFINITY);
+            assert!(rec.is_hit && rec.front_face, "{}", name);
+            assert!((rec.t - 2.0 / 3.0).abs() < 1e-5, "{}", name);
+
+            let rec = mesh.hit(&Ray::new(Point3::new(0.2, 0.2, -1.0), Vec3::new(0.0, 0.0, 1.0)), 0.001, f32::INFINITY);
+            assert!(rec.is_hit && rec.front_face, "{}", name);
+        }
+    }
+}
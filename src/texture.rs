@@ -0,0 +1,320 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{Color, Point3};
+use crate::image::{load_image, Image};
+
+/// A colour that varies over a surface, looked up by the hit's surface
+/// coordinates and position. Scalar material parameters read the red channel.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f32, v: f32, p: &Point3) -> Color;
+}
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f32, v: f32, p: &Point3) -> Color {
+        (**self).value(u, v, p)
+    }
+}
+
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> SolidColor {
+        SolidColor { color }
+    }
+
+    /// A grey of the given value, for scalar parameters.
+    pub fn scalar(value: f32) -> SolidColor {
+        SolidColor::new(Color::new(value, value, value))
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _p: &Point3) -> Color {
+        self.color.clone()
+    }
+}
+
+/// Alternating squares laid out in surface coordinates, `squares` to each
+/// unit of u and v.
+pub struct Checker2D {
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    squares: f32,
+}
+
+impl Checker2D {
+    pub fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, squares: f32) -> Checker2D {
+        Checker2D { even, odd, squares }
+    }
+}
+
+impl Texture for Checker2D {
+    fn value(&self, u: f32, v: f32, p: &Point3) -> Color {
+        let parity = (u * self.squares).floor() as i64 + (v * self.squares).floor() as i64;
+        if parity.rem_euclid(2) == 0 { self.even.value(u, v, p) } else { self.odd.value(u, v, p) }
+    }
+}
+
+/// Alternating cubes of side `size` filling space, so objects look carved
+/// out of a checkered block whatever their surface coordinates.
+pub struct Checker3D {
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    size: f32,
+}
+
+impl Checker3D {
+    pub fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, size: f32) -> Checker3D {
+        Checker3D { even, odd, size }
+    }
+}
+
+impl Texture for Checker3D {
+    fn value(&self, u: f32, v: f32, p: &Point3) -> Color {
+        let cell = |coordinate: f32| (coordinate / self.size).floor() as i64;
+        let parity = cell(p.x()) + cell(p.y()) + cell(p.z());
+        if parity.rem_euclid(2) == 0 { self.even.value(u, v, p) } else { self.odd.value(u, v, p) }
+    }
+}
+
+/// Another texture multiplied by a constant colour, such as a base colour
+/// factor applied to an image.
+pub struct ScaledTexture {
+    texture: Arc<dyn Texture>,
+    factor: Color,
+}
+
+impl ScaledTexture {
+    pub fn new(texture: Arc<dyn Texture>, factor: Color) -> ScaledTexture {
+        ScaledTexture { texture, factor }
+    }
+}
+
+impl Texture for ScaledTexture {
+    fn value(&self, u: f32, v: f32, p: &Point3) -> Color {
+        self.texture.value(u, v, p) * self.factor.clone()
+    }
+}
+
+/// One channel of another texture, as a grey. Lets a scalar parameter read a
+/// channel other than red, as when several maps are packed into one image.
+pub struct ChannelTexture {
+    texture: Arc<dyn Texture>,
+    channel: usize,
+}
+
+impl ChannelTexture {
+    pub fn new(texture: Arc<dyn Texture>, channel: usize) -> ChannelTexture {
+        assert!(channel < 3, "channel must be 0, 1 or 2");
+        ChannelTexture { texture, channel }
+    }
+}
+
+impl Texture for ChannelTexture {
+    fn value(&self, u: f32, v: f32, p: &Point3) -> Color {
+        let value = self.texture.value(u, v, p)[self.channel];
+        Color::new(value, value, value)
+    }
+}
+
+/// How surface coordinates outside [0, 1] map onto an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl WrapMode {
+    fn apply(self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::MirroredRepeat => {
+                let index = index.rem_euclid(2 * size);
+                if index < size { index } else { 2 * size - 1 - index }
+            }
+            WrapMode::ClampToEdge => index.clamp(0, size - 1),
+        };
+        wrapped as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+/// An image stretched over [0, 1] in u and v, with v = 0 along its bottom
+/// row. It repeats and is filtered bilinearly unless told otherwise.
+pub struct ImageTexture {
+    image: Image,
+    wrap: WrapMode,
+    filter: Filter,
+}
+
+impl ImageTexture {
+    /// The image must hold linear values.
+    pub fn new(image: Image) -> ImageTexture {
+        assert!(image.width() > 0 && image.height() > 0, "texture image is empty");
+        ImageTexture { image, wrap: WrapMode::Repeat, filter: Filter::Bilinear }
+    }
+
+    /// Loads a colour image. PNG and JPEG files are taken to be sRGB encoded
+    /// and are converted to linear values; HDR and PFM files already are.
+    pub fn load(path: &Path) -> io::Result<ImageTexture> {
+        let image = load_image(path)?;
+        let is_srgb = path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| matches!(extension.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg"));
+
+        Ok(if is_srgb { ImageTexture::new(linearize(&image)) } else { ImageTexture::new(image) })
+    }
+
+    /// Loads an image of data, such as roughness or normals, whose values are
+    /// used as stored.
+    pub fn load_linear(path: &Path) -> io::Result<ImageTexture> {
+        load_image(path).map(ImageTexture::new)
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> ImageTexture {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> ImageTexture {
+        self.filter = filter;
+        self
+    }
+
+    /// The mean of the texels, for surfaces with no coordinates to look the
+    /// image up by.
+    pub fn average(&self) -> Color {
+        let pixels = self.image.pixels();
+        pixels.iter().fold(Color::origin(), |sum, pixel| sum + pixel.clone()) / pixels.len() as f32
+    }
+
+    fn texel(&self, x: i64, y: i64) -> &Color {
+        self.image.pixel(self.wrap.apply(x, self.image.width()), self.wrap.apply(y, self.image.height()))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _p: &Point3) -> Color {
+        // Rows run from the top of the image, while v runs up from the bottom.
+        // Coordinates that are not finite, or overflow once scaled, look up the
+        // first texel instead.
+        let x = finite_or_zero(u * self.image.width() as f32);
+        let y = finite_or_zero((1.0 - v) * self.image.height() as f32);
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64).clone(),
+            Filter::Bilinear => {
+                // Texel centres lie half a texel in from their corners.
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                // Huge coordinates saturate, so the next texel must not overflow.
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let (x1, y1) = (x0.saturating_add(1), y0.saturating_add(1));
+
+                let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x1, y0) * fx;
+                let bottom = self.texel(x0, y1) * (1.0 - fx) + self.texel(x1, y1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            }
+        }
+    }
+}
+
+fn finite_or_zero(coordinate: f32) -> f32 {
+    if coordinate.is_finite() { coordinate } else { 0.0 }
+}
+
+fn linearize(image: &Image) -> Image {
+    let pixels = image.pixels().iter().map(|pixel| pixel.srgb_to_linear()).collect();
+    Image::new(image.width(), image.height(), pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Color, Point3};
+    use crate::texture::{Checker2D, Checker3D, Filter, ImageTexture, SolidColor, Texture, WrapMode};
+    use crate::utils::fixture;
+
+    fn close(a: &Color, b: &Color) -> bool {
+        (a - b).length() < 1e-5
+    }
+
+    #[test]
+    fn checkers_alternate_in_surface_coordinates_and_in_space() {
+        let black = Arc::new(SolidColor::new(Color::new(0.0, 0.0, 0.0)));
+        let white = Arc::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+        let origin = Point3::origin();
+
+        let checker = Checker2D::new(black.clone(), white.clone(), 4.0);
+        assert_eq!(checker.value(0.1, 0.1, &origin).x(), 0.0);
+        assert_eq!(checker.value(0.3, 0.1, &origin).x(), 1.0);
+        assert_eq!(checker.value(0.3, 0.3, &origin).x(), 0.0);
+
+        let checker = Checker3D::new(black, white, 0.5);
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(0.25, 0.25, 0.25)).x(), 0.0);
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(0.75, 0.25, 0.25)).x(), 1.0);
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(-0.25, 0.25, 0.25)).x(), 1.0);
+    }
+
+    // quad.png is red and green along its top row, blue and white along its
+    // bottom one.
+    #[test]
+    fn images_are_filtered_bilinearly_between_texel_centres() {
+        let texture = ImageTexture::load(&fixture("quad.png")).unwrap().with_wrap(WrapMode::ClampToEdge);
+        let origin = Point3::origin();
+
+        assert!(close(&texture.value(0.25, 0.75, &origin), &Color::new(1.0, 0.0, 0.0)));
+        assert!(close(&texture.value(0.75, 0.25, &origin), &Color::new(1.0, 1.0, 1.0)));
+        assert!(close(&texture.value(0.5, 0.75, &origin), &Color::new(0.5, 0.5, 0.0)));
+        assert!(close(&texture.value(0.5, 0.5, &origin), &Color::new(0.5, 0.5, 0.5)));
+        // Clamping holds the edge texels beyond the image.
+        assert!(close(&texture.value(-1.0, 2.0, &origin), &Color::new(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn wrap_modes_repeat_mirror_or_clamp() {
+        let texture = |wrap| ImageTexture::load(&fixture("quad.png")).unwrap().with_wrap(wrap).with_filter(Filter::Nearest);
+        let origin = Point3::origin();
+        let red = Color::new(1.0, 0.0, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+
+        // Just right of the image's right edge, on its top row.
+        assert!(close(&texture(WrapMode::Repeat).value(1.1, 0.9, &origin), &red));
+        assert!(close(&texture(WrapMode::MirroredRepeat).value(1.1, 0.9, &origin), &green));
+        assert!(close(&texture(WrapMode::ClampToEdge).value(1.1, 0.9, &origin), &green));
+        // And further out, where mirroring has flipped back.
+        assert!(close(&texture(WrapMode::MirroredRepeat).value(2.1, 0.9, &origin), &red));
+        assert!(close(&texture(WrapMode::ClampToEdge).value(2.1, 0.9, &origin), &green));
+    }
+
+    #[test]
+    fn coordinates_out_of_range_give_finite_texels() {
+        let origin = Point3::origin();
+        for wrap in [WrapMode::Repeat, WrapMode::MirroredRepeat, WrapMode::ClampToEdge] {
+            for filter in [Filter::Nearest, Filter::Bilinear] {
+                let texture = ImageTexture::load(&fixture("quad.png")).unwrap().with_wrap(wrap).with_filter(filter);
+                let corner = texture.value(0.0, 1.0, &origin);
+                for (u, v) in [(f32::NAN, 1.0), (f32::NEG_INFINITY, f32::NAN), (f32::NAN, f32::MIN)] {
+                    assert!(close(&texture.value(u, v, &origin), &corner));
+                }
+                for (u, v) in [(1e30, -1e30), (f32::MAX, f32::MIN), (-1e30, 0.3)] {
+                    let value = texture.value(u, v, &origin);
+                    assert!(value.x().is_finite() && value.y().is_finite() && value.z().is_finite());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use std::ops::Mul;
+
+use crate::{Point3, Ray, Vec3};
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+
+/// A 4x4 matrix acting on column vectors, stored row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix4 {
+    rows: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    pub const IDENTITY: Matrix4 = Matrix4 {
+        rows: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub const fn new(rows: [[f32; 4]; 4]) -> Matrix4 {
+        Matrix4 { rows }
+    }
+
+    /// Builds a matrix from its columns, the order used by glTF and OpenGL.
+    pub fn from_columns(columns: [[f32; 4]; 4]) -> Matrix4 {
+        Matrix4 { rows: columns }.transpose()
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.rows[row][column]
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rows[j][i];
+            }
+        }
+        Matrix4 { rows }
+    }
+
+    /// The determinant of the upper 3x3 part, which scales volumes. It is
+    /// negative for transforms that mirror.
+    pub fn linear_determinant(&self) -> f32 {
+        let m = &self.rows;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Inverts the matrix by Gauss-Jordan elimination, or returns `None` if
+    /// it is singular.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let mut left = self.rows;
+        let mut right = Matrix4::IDENTITY.rows;
+
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|a, b| left[*a][column].abs().total_cmp(&left[*b][column].abs()))
+                .unwrap();
+            if left[pivot][column].abs() < f32::EPSILON {
+                return None;
+            }
+            left.swap(column, pivot);
+            right.swap(column, pivot);
+
+            let scale = 1.0 / left[column][column];
+            for k in 0..4 {
+                left[column][k] *= scale;
+                right[column][k] *= scale;
+            }
+
+            for row in 0..4 {
+                let factor = left[row][column];
+                if row == column || factor == 0.0 {
+                    continue;
+                }
+                for k in 0..4 {
+                    left[row][k] -= factor * left[column][k];
+                    right[row][k] -= factor * right[column][k];
+                }
+            }
+        }
+
+        Some(Matrix4 { rows: right })
+    }
+
+    /// Transforms a point, including the translation. The matrix is taken to
+    /// be affine, so the bottom row is ignored.
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        let m = &self.rows;
+        Point3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    /// Transforms a direction, which is unaffected by translation.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.rows;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+
+    /// Transforms a surface normal so it stays perpendicular to the
+    /// transformed surface, even under non-uniform scaling. The result is not
+    /// normalised. It uses the cofactors of the upper 3x3 part, which equal
+    /// the inverse transpose up to a positive factor once the sign of the
+    /// determinant is applied, and which exist even for singular matrices.
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        let column = |j: usize| Vec3::new(self.rows[0][j], self.rows[1][j], self.rows[2][j]);
+        let (c0, c1, c2) = (column(0), column(1), column(2));
+        let sign = if self.linear_determinant() < 0.0 { -1.0 } else { 1.0 };
+        (c1.cross(&c2) * n.x() + c2.cross(&c0) * n.y() + c0.cross(&c1) * n.z()) * sign
+    }
+}
+
+impl Mul for &Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: &Matrix4) -> Matrix4 {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.rows[i][k] * other.rows[k][j]).sum();
+            }
+        }
+        Matrix4 { rows }
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        &self * &other
+    }
+}
+
+/// An invertible affine transform, kept together with its inverse.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform { matrix: Matrix4::IDENTITY, inverse: Matrix4::IDENTITY }
+    }
+
+    /// Returns `None` if the matrix cannot be inverted.
+    pub fn from_matrix(matrix: Matrix4) -> Option<Transform> {
+        let inverse = matrix.inverse()?;
+        Some(Transform { matrix, inverse })
+    }
+
+    pub fn translation(offset: &Vec3) -> Transform {
+        let translate = |x: f32, y: f32, z: f32| Matrix4::new([
+            [1.0, 0.0, 0.0, x],
+            [0.0, 1.0, 0.0, y],
+            [0.0, 0.0, 1.0, z],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        Transform {
+            matrix: translate(offset.x(), offset.y(), offset.z()),
+            inverse: translate(-offset.x(), -offset.y(), -offset.z()),
+        }
+    }
+
+    /// Scales by a factor along each axis. A negative factor mirrors.
+    pub fn scaling(factors: &Vec3) -> Transform {
+        assert!(
+            factors.x() != 0.0 && factors.y() != 0.0 && factors.z() != 0.0,
+            "scale factors must not be zero"
+        );
+        let scale = |x: f32, y: f32, z: f32| Matrix4::new([
+            [x, 0.0, 0.0, 0.0],
+            [0.0, y, 0.0, 0.0],
+            [0.0, 0.0, z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        Transform {
+            matrix: scale(factors.x(), factors.y(), factors.z()),
+            inverse: scale(1.0 / factors.x(), 1.0 / factors.y(), 1.0 / factors.z()),
+        }
+    }
+
+    pub fn uniform_scaling(factor: f32) -> Transform {
+        Transform::scaling(&Vec3::new(factor, factor, factor))
+    }
+
+    /// Rotates anticlockwise by `degrees` when looking down `axis` towards
+    /// the origin.
+    pub fn rotation(axis: &Vec3, degrees: f32) -> Transform {
+        let a = axis.unit_vector();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        let matrix = Matrix4::new([
+            [t * a.x() * a.x() + cos, t * a.x() * a.y() - sin * a.z(), t * a.x() * a.z() + sin * a.y(), 0.0],
+            [t * a.x() * a.y() + sin * a.z(), t * a.y() * a.y() + cos, t * a.y() * a.z() - sin * a.x(), 0.0],
+            [t * a.x() * a.z() - sin * a.y(), t * a.y() * a.z() + sin * a.x(), t * a.z() * a.z() + cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        // Rotations are orthogonal, so the inverse is the transpose.
+        let inverse = matrix.transpose();
+        Transform { matrix, inverse }
+    }
+
+    pub fn rotation_x(degrees: f32) -> Transform {
+        Transform::rotation(&Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotation_y(degrees: f32) -> Transform {
+        Transform::rotation(&Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotation_z(degrees: f32) -> Transform {
+        Transform::rotation(&Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    /// The transform that applies `self` and then `next`.
+    pub fn then(self, next: &Transform) -> Transform {
+        Transform {
+            matrix: &next.matrix * &self.matrix,
+            inverse: &self.inverse * &next.inverse,
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform { matrix: self.inverse.clone(), inverse: self.matrix.clone() }
+    }
+
+    pub fn matrix(&self) -> &Matrix4 {
+        &self.matrix
+    }
+
+    pub fn inverse_matrix(&self) -> &Matrix4 {
+        &self.inverse
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        self.matrix.transform_point(p)
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.matrix.transform_vector(v)
+    }
+
+    /// Transforms a surface normal as `Matrix4::transform_normal` does and
+    /// normalises it.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        self.matrix.transform_normal(n).unit_vector()
+    }
+
+    /// The ray's direction is not normalised, so distances along it keep
+    /// their parameter `t`.
+    pub fn ray(&self, ray: &Ray) -> Ray {
+        Ray::new(self.point(&ray.orig), self.vector(&ray.dir))
+    }
+
+    /// A box around the transformed corners of `bounding_box`.
+    pub fn bounding_box(&self, bounding_box: &Aabb) -> Aabb {
+        let (minimum, maximum) = (&bounding_box.minimum, &bounding_box.maximum);
+        if minimum.x() > maximum.x() || minimum.y() > maximum.y() || minimum.z() > maximum.z() {
+            return Aabb::empty();
+        }
+
+        (0..8).fold(Aabb::empty(), |accum, corner| {
+            let p = self.point(&Point3::new(
+                if corner & 1 == 0 { minimum.x() } else { maximum.x() },
+                if corner & 2 == 0 { minimum.y() } else { maximum.y() },
+                if corner & 4 == 0 { minimum.z() } else { maximum.z() },
+            ));
+            Aabb::surrounding_box(&accum, &Aabb::new(p.clone(), p))
+        })
+    }
+}
+
+/// An object moved by a transform. Rays are taken into the object's own space
+/// to be intersected, and the hit is brought back out.
+///
+/// Wrapping an `Arc` instances the object: every copy shares the geometry, so
+/// a thousand placements of one mesh hold a single copy of its triangles.
+///
+/// ```
+/// use std::sync::Arc;
+/// use ray_trace::{Color, Point3, Vec3};
+/// use ray_trace::hittable_list::World;
+/// use ray_trace::material::Lambertian;
+/// use ray_trace::sphere::Sphere;
+/// use ray_trace::transform::{Transform, Transformed};
+///
+/// let ball = Arc::new(Sphere::new(Point3::origin(), 1.0, Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+/// let world = (0..10).fold(World::new(), |world, i| {
+///     let transform = Transform::translation(&Vec3::new(3.0 * i as f32, 0.0, 0.0));
+///     world.add(Box::new(Transformed::new(Arc::clone(&ball), transform)))
+/// });
+/// ```
+pub struct Transformed<H: Hittable> {
+    object: H,
+    transform: Transform,
+    bounding_box: Aabb,
+}
+
+impl<H: Hittable> Transformed<H> {
+    pub fn new(object: H, transform: Transform) -> Transformed<H> {
+        let bounding_box = transform.bounding_box(&object.bounding_box());
+        Transformed { object, transform, bounding_box }
+    }
+
+    pub fn object(&self) -> &H {
+        &self.object
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+impl<H: Hittable> Hittable for Transformed<H> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
+        let inverse = self.transform.inverse_matrix();
+        let local_ray = Ray::new(inverse.transform_point(&ray.orig), inverse.transform_vector(&ray.dir));
+        let rec = self.object.hit(&local_ray, t_min, t_max);
+        if !rec.is_hit {
+            return rec;
+        }
+
+        // Normals transform so that their dot product with the ray's direction
+        // keeps its sign, so the hit stays on the same face.
+        HitRecord {
+            p: self.transform.point(&rec.p),
+            normal: self.transform.normal(&rec.normal),
+            dpdu: self.transform.vector(&rec.dpdu),
+            dpdv: self.transform.vector(&rec.dpdv),
+            ..rec
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box.clone()
+    }
+
+    // The object's density over its own directions is carried over by the
+    // Jacobian of the map between the two spheres of directions, which for a
+    // linear map A and unit direction u is |det A| / |A u|^3.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f32 {
+        let inverse = self.transform.inverse_matrix();
+        let local_direction = inverse.transform_vector(direction).unit_vector();
+        let local_pdf = self.object.pdf_value(&inverse.transform_point(origin), &local_direction);
+        if local_pdf <= 0.0 {
+            return 0.0;
+        }
+
+        let matrix = self.transform.matrix();
+        let stretch = matrix.transform_vector(&local_direction).length();
+        local_pdf * stretch * stretch * stretch / matrix.linear_determinant().abs()
+    }
+
+    fn random_direction(&self, origin: &Point3) -> Vec3 {
+        let local_origin = self.transform.inverse_matrix().transform_point(origin);
+        self.transform.vector(&self.object.random_direction(&local_origin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::{Point3, Vec3};
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::transform::{Matrix4, Transform, Transformed};
+    use crate::utils::seed_rng;
+
+    fn unit_sphere() -> Sphere<'static> {
+        Sphere::new(Point3::origin(), 1.0, Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))
+    }
+
+    fn assert_near(a: &Matrix4, b: &Matrix4) {
+        for row in 0..4 {
+            for column in 0..4 {
+                assert!((a.get(row, column) - b.get(row, column)).abs() < 1e-5, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn composed_transforms_keep_their_inverse() {
+        let transform = Transform::scaling(&Vec3::new(2.0, -3.0, 0.5))
+            .then(&Transform::rotation(&Vec3::new(1.0, 2.0, -1.0), 37.0))
+            .then(&Transform::translation(&Vec3::new(4.0, -1.0, 2.0)));
+
+        assert_near(&(transform.matrix() * transform.inverse_matrix()), &Matrix4::IDENTITY);
+        assert_near(&transform.matrix().inverse().unwrap(), transform.inverse_matrix());
+        assert!(Matrix4::new([[1.0, 2.0, 0.0, 0.0], [2.0, 4.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]])
+            .inverse()
+            .is_none());
+
+        let rotated = Transform::rotation_z(90.0).point(&Point3::new(1.0, 0.0, 0.0));
+        assert!((&rotated - &Point3::new(0.0, 1.0, 0.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn transformed_sphere_matches_a_moved_sphere() {
+        seed_rng(21);
+        let transformed = Transformed::new(
+            unit_sphere(),
+            Transform::uniform_scaling(2.0)
+                .then(&Transform::rotation_y(30.0))
+                .then(&Transform::translation(&Vec3::new(3.0, 1.0, -2.0))),
+        );
+        let moved = Sphere::new(Point3::new(3.0, 1.0, -2.0), 2.0, Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))));
+
+        for _ in 0..1000 {
+            let ray = Ray::new(Vec3::random_range(-8.0, 8.0), Vec3::random_unit_vector());
+            let expected = moved.hit(&ray, 0.001, f32::INFINITY);
+            let actual = transformed.hit(&ray, 0.001, f32::INFINITY);
+
+            assert_eq!(expected.is_hit, actual.is_hit);
+            if expected.is_hit {
+                assert!((expected.t - actual.t).abs() < 1e-4);
+                assert!((&expected.p - &actual.p).length() < 1e-3);
+                assert!((&expected.normal - &actual.normal).length() < 1e-3);
+                assert_eq!(expected.front_face, actual.front_face);
+            }
+        }
+    }
+
+    #[test]
+    fn non_uniform_scaling_keeps_normals_perpendicular() {
+        // The ellipsoid x^2 / 4 + y^2 = 1 in the z = 0 plane.
+        let ellipsoid = Transformed::new(unit_sphere(), Transform::scaling(&Vec3::new(2.0, 1.0, 1.0)));
+        let rec = ellipsoid.hit(&Ray::new(Point3::origin(), Vec3::new(1.0, 1.0, 0.0)), 0.001, f32::INFINITY);
+
+        assert!(rec.is_hit && !rec.front_face);
+        let expected = Vec3::new(rec.p.x() / 4.0, rec.p.y(), 0.0).unit_vector();
+        assert!((&rec.normal + &expected).length() < 1e-5);
+        assert!((ellipsoid.bounding_box().maximum.x() - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn transformed_light_density_integrates_to_one() {
+        seed_rng(22);
+        let light = Transformed::new(
+            unit_sphere(),
+            Transform::scaling(&Vec3::new(3.0, 0.5, 1.0))
+                .then(&Transform::rotation_x(40.0))
+                .then(&Transform::translation(&Vec3::new(0.0, 0.0, -4.0))),
+        );
+        let origin = Point3::new(0.5, 0.2, 0.0);
+
+        let samples = 400_000;
+        let total: f32 = (0..samples)
+            .map(|_| light.pdf_value(&origin, &Vec3::random_unit_vector()))
+            .sum();
+        let integral = total / samples as f32 * 4.0 * PI;
+        assert!((integral - 1.0).abs() < 0.02, "integral {}", integral);
+
+        for _ in 0..100 {
+            let direction = light.random_direction(&origin);
+            assert!(light.hit(&Ray::new(origin.clone(), direction), 0.001, f32::INFINITY).is_hit);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::ops::Deref;
+
+use crate::{Point3, Ray, Vec3};
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::utils::random_float;
+
+pub struct Triangle<'a> {
+    vertices: [Point3; 3],
+    material: Box<dyn Material + 'a>,
+}
+
+impl<'a> Triangle<'_> {
+    /// The front face is the one the vertices wind anticlockwise around.
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: Box<dyn Material + 'a>) -> Triangle<'a> {
+        Triangle { vertices: [v0, v1, v2], material }
+    }
+
+    fn area(&self) -> f32 {
+        let [v0, v1, v2] = &self.vertices;
+        (v1 - v0).cross(&(v2 - v0)).length() / 2.0
+    }
+}
+
+impl Hittable for Triangle<'_> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
+        let [v0, v1, v2] = &self.vertices;
+        let (t, b1, b2) = match intersect(v0, v1, v2, ray, t_min, t_max) {
+            Some(hit) => hit,
+            None => return HitRecord::miss(),
+        };
+
+        let outward_normal = (v1 - v0).cross(&(v2 - v0)).unit_vector();
+        let (front_face, normal) = HitRecord::get_face_normal(ray, outward_normal);
+
+        HitRecord {
+            p: ray.at(t),
+            normal,
+            material: self.material.deref(),
+            t,
+            u: b1,
+            v: b2,
+            dpdu: v1 - v0,
+            dpdv: v2 - v0,
+            vertex_color: None,
+            front_face,
+            is_hit: true,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        triangle_bounding_box(&self.vertices[0], &self.vertices[1], &self.vertices[2])
+    }
+
+    // Points are sampled uniformly over the triangle's area.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f32 {
+        let [v0, v1, v2] = &self.vertices;
+        match intersect(v0, v1, v2, &Ray::new(origin.clone(), direction.clone()), 0.001, f32::INFINITY) {
+            Some((t, _, _)) => area_density(direction, t, &(v1 - v0).cross(&(v2 - v0)), self.area()),
+            None => 0.0,
+        }
+    }
+
+    fn random_direction(&self, origin: &Point3) -> Vec3 {
+        let [v0, v1, v2] = &self.vertices;
+        random_point(v0, v1, v2) - origin
+    }
+}
+
+/// The solid angle density of a point found at `t` along `direction`, on a
+/// surface with the given normal that is sampled uniformly over `area`.
+pub(crate) fn area_density(direction: &Vec3, t: f32, normal: &Vec3, area: f32) -> f32 {
+    let distance_squared = t * t * direction.length_squared();
+    let cosine = (direction.dot(normal) / (direction.length() * normal.length())).abs();
+    if cosine <= 0.0 || area <= 0.0 {
+        return 0.0;
+    }
+    distance_squared / (cosine * area)
+}
+
+/// A point picked uniformly over the triangle's area.
+pub(crate) fn random_point(v0: &Point3, v1: &Point3, v2: &Point3) -> Point3 {
+    let su = random_float().sqrt();
+    let b1 = random_float() * su;
+    let b0 = 1.0 - su;
+    v0 * b0 + v1 * b1 + v2 * (1.0 - b0 - b1)
+}
+
+/// Intersects a ray with a triangle using the Möller–Trumbore algorithm,
+/// returning the ray parameter and the barycentric weights of `v1` and `v2`.
+pub(crate) fn intersect(v0: &Point3, v1: &Point3, v2: &Point3, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+    let edge1 = v1 - v0;
+    let edge2 = v2 - v0;
+
+    let pvec = ray.dir.cross(&edge2);
+    let det = edge1.dot(&pvec);
+    // The determinant shrinks with the triangle, so only exactly parallel rays
+    // are rejected here; nearly parallel ones fail the bounds checks below.
+    if det == 0.0 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = &ray.orig - v0;
+    let b1 = tvec.dot(&pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = tvec.cross(&edge1);
+    let b2 = ray.dir.dot(&qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&qvec) * inv_det;
+    if t < t_min || t_max < t {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+/// The box around a triangle, padded so that triangles lying in an axis plane
+/// still have some thickness.
+pub(crate) fn triangle_bounding_box(v0: &Point3, v1: &Point3, v2: &Point3) -> Aabb {
+    const PADDING: f32 = 1e-4;
+    let padding = Vec3::new(PADDING, PADDING, PADDING);
+
+    let minimum = Point3::new(
+        v0.x().min(v1.x()).min(v2.x()),
+        v0.y().min(v1.y()).min(v2.y()),
+        v0.z().min(v1.z()).min(v2.z()),
+    );
+    let maximum = Point3::new(
+        v0.x().max(v1.x()).max(v2.x()),
+        v0.y().max(v1.y()).max(v2.y()),
+        v0.z().max(v1.z()).max(v2.z()),
+    );
+    Aabb::new(minimum - &padding, maximum + padding)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::{Color, Point3, Vec3};
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::triangle::Triangle;
+    use crate::utils::seed_rng;
+
+    #[test]
+    fn tiny_triangles_are_hit() {
+        for size in [1e-4, 3e-5, 1e-5, 1e-7] {
+            let triangle = Triangle::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(size, 0.0, 0.0),
+                Point3::new(0.0, size, 0.0),
+                Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+            );
+            let ray = Ray::new(Point3::new(size / 4.0, size / 4.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+            let rec = triangle.hit(&ray, 0.001, f32::INFINITY);
+            assert!(rec.is_hit && (rec.t - 1.0).abs() < 1e-5, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn light_density_integrates_to_one() {
+        seed_rng(16);
+        let light = Triangle::new(
+            Point3::new(-1.0, 2.0, -3.0),
+            Point3::new(2.0, 1.0, -2.5),
+            Point3::new(0.0, 3.0, -1.5),
+            Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+        let origin = Point3::new(0.2, 0.1, 0.0);
+
+        let samples = 400_000;
+        let total: f32 = (0..samples)
+            .map(|_| light.pdf_value(&origin, &Vec3::random_unit_vector()))
+            .sum();
+        let integral = total / samples as f32 * 4.0 * PI;
+        assert!((integral - 1.0).abs() < 0.02, "integral {}", integral);
+
+        for _ in 0..100 {
+            let direction = light.random_direction(&origin);
+            let rec = light.hit(&Ray::new(origin.clone(), direction.clone()), 0.001, f32::INFINITY);
+            // The direction runs from the origin to a point on the triangle.
+            assert!(rec.is_hit && (rec.t - 1.0).abs() < 1e-4);
+            assert!(light.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
+}
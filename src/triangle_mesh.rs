@@ -0,0 +1,428 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::{Color, Point3, Ray, Vec3};
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::material::Material;
+use crate::triangle::{area_density, intersect, random_point, triangle_bounding_box};
+use crate::utils::random_float;
+
+/// Triangles sharing arrays of vertex positions, and optionally normals and
+/// texture coordinates, that all use one material. The mesh keeps its own BVH
+/// over the triangles, so it can be placed in a scene as a single object.
+///
+/// As a light, the mesh is sampled uniformly over its area: a triangle is
+/// picked in proportion to its area, then a point on it.
+pub struct TriangleMesh<'a> {
+    bvh: BvhNode<MeshTriangle<'a>>,
+    mesh: Arc<MeshData<'a>>,
+    triangles: Vec<[usize; 3]>,
+    // The running total of the triangles' areas, ending with the mesh's area.
+    cumulative_areas: Vec<f32>,
+}
+
+/// Why the arrays given for a mesh do not fit together.
+#[derive(Debug)]
+pub enum MeshError {
+    /// Per-vertex data that is neither empty nor one entry per position.
+    MismatchedArray {
+        name: &'static str,
+        length: usize,
+        vertex_count: usize,
+    },
+    /// A triangle index past the end of the positions.
+    MissingVertex(usize),
+}
+
+impl Display for MeshError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::MismatchedArray { name, length, vertex_count } => {
+                write!(f, "{} {} given for {} vertices", length, name, vertex_count)
+            }
+            MeshError::MissingVertex(index) => write!(f, "triangle refers to missing vertex {}", index),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+struct MeshData<'a> {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    colors: Vec<Color>,
+    material: Box<dyn Material + 'a>,
+}
+
+struct MeshTriangle<'a> {
+    mesh: Arc<MeshData<'a>>,
+    indices: [usize; 3],
+}
+
+impl<'a> TriangleMesh<'_> {
+    /// `indices` holds three vertex indices per triangle. `normals` and `uvs`
+    /// are either empty or hold one entry per position; without normals the
+    /// mesh is flat shaded, and without UVs the barycentric coordinates are used.
+    /// Fails if the arrays do not fit together.
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f32, f32)>,
+        indices: Vec<[usize; 3]>,
+        material: Box<dyn Material + 'a>,
+    ) -> Result<TriangleMesh<'a>, MeshError> {
+        TriangleMesh::colored(positions, normals, uvs, vec![], indices, material)
+    }
+
+    /// As `new`, with `colors` either empty or holding one colour per position.
+    /// The colour interpolated across a triangle is passed on to its material
+    /// through `HitRecord::vertex_color`.
+    pub fn colored(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f32, f32)>,
+        colors: Vec<Color>,
+        indices: Vec<[usize; 3]>,
+        material: Box<dyn Material + 'a>,
+    ) -> Result<TriangleMesh<'a>, MeshError> {
+        let vertex_count = positions.len();
+        for (name, length) in [("normals", normals.len()), ("UVs", uvs.len()), ("colours", colors.len())] {
+            if length != 0 && length != vertex_count {
+                return Err(MeshError::MismatchedArray { name, length, vertex_count });
+            }
+        }
+        if let Some(index) = indices.iter().flatten().find(|index| **index >= vertex_count) {
+            return Err(MeshError::MissingVertex(*index));
+        }
+
+        let mesh = Arc::new(MeshData {
+            positions,
+            // Zero normals are kept as they are, rather than normalized to NaN,
+            // so hits near them can fall back to the face normal.
+            normals: normals.into_iter()
+                .map(|normal| if normal.near_zero() { normal } else { normal.unit_vector() })
+                .collect(),
+            uvs,
+            colors,
+            material,
+        });
+
+        let cumulative_areas = indices.iter()
+            .scan(0.0, |total, [i0, i1, i2]| {
+                let [v0, v1, v2] = [&mesh.positions[*i0], &mesh.positions[*i1], &mesh.positions[*i2]];
+                *total += (v1 - v0).cross(&(v2 - v0)).length() / 2.0;
+                Some(*total)
+            })
+            .collect();
+        let bvh = BvhNode::new(indices.iter().fold(HittableList::new(), |triangles, indices| {
+            triangles.add(MeshTriangle { mesh: Arc::clone(&mesh), indices: *indices })
+        }));
+
+        Ok(TriangleMesh { bvh, mesh, triangles: indices, cumulative_areas })
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.mesh.material.is_emissive()
+    }
+
+    fn area(&self) -> f32 {
+        self.cumulative_areas.last().copied().unwrap_or(0.0)
+    }
+}
+
+impl Hittable for TriangleMesh<'_> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    // Every triangle along the direction could have been sampled, not only
+    // the nearest.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f32 {
+        let area = self.area();
+        if area <= 0.0 {
+            return 0.0;
+        }
+        density_along(&self.bvh, &Ray::new(origin.clone(), direction.clone()), area)
+    }
+
+    fn random_direction(&self, origin: &Point3) -> Vec3 {
+        let area = self.area();
+        if area <= 0.0 {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+
+        let target = random_float() * area;
+        let index = self.cumulative_areas.partition_point(|total| *total <= target).min(self.triangles.len() - 1);
+        let [i0, i1, i2] = self.triangles[index];
+        let positions = &self.mesh.positions;
+        random_point(&positions[i0], &positions[i1], &positions[i2]) - origin
+    }
+}
+
+// Sums the densities of the points on every triangle the ray passes through,
+// each sampled with probability in proportion to its share of `area`.
+fn density_along(node: &BvhNode<MeshTriangle>, ray: &Ray, area: f32) -> f32 {
+    match node {
+        BvhNode::Leaf(triangle) => {
+            let [v0, v1, v2] = triangle.positions();
+            match intersect(v0, v1, v2, ray, 0.001, f32::INFINITY) {
+                Some((t, _, _)) => area_density(&ray.dir, t, &(v1 - v0).cross(&(v2 - v0)), area),
+                None => 0.0,
+            }
+        }
+        BvhNode::Branch { left, right, bounding_box } => {
+            if bounding_box.hit(ray, 0.001, f32::INFINITY) {
+                density_along(left, ray, area) + density_along(right, ray, area)
+            } else {
+                0.0
+            }
+        }
+        BvhNode::Empty => 0.0,
+    }
+}
+
+impl MeshTriangle<'_> {
+    fn positions(&self) -> [&Point3; 3] {
+        let [i0, i1, i2] = self.indices;
+        [&self.mesh.positions[i0], &self.mesh.positions[i1], &self.mesh.positions[i2]]
+    }
+}
+
+impl Hittable for MeshTriangle<'_> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> HitRecord<'_> {
+        let [v0, v1, v2] = self.positions();
+        let (t, b1, b2) = match intersect(v0, v1, v2, ray, t_min, t_max) {
+            Some(hit) => hit,
+            None => return HitRecord::miss(),
+        };
+        let b0 = 1.0 - b1 - b2;
+        let [i0, i1, i2] = self.indices;
+        let mesh = &self.mesh;
+
+        // Which side was hit follows the true surface, even where the shading
+        // normal leans away from it.
+        let geometric_normal = (v1 - v0).cross(&(v2 - v0));
+        let front_face = ray.dir.dot(&geometric_normal) < 0.0;
+        let shading_normal = if mesh.normals.is_empty() {
+            None
+        } else {
+            Some(&mesh.normals[i0] * b0 + &mesh.normals[i1] * b1 + &mesh.normals[i2] * b2)
+        };
+        let outward_normal = match shading_normal {
+            Some(normal) if !normal.near_zero() => normal.unit_vector(),
+            _ => geometric_normal.unit_vector(),
+        };
+        let vertex_color = if mesh.colors.is_empty() {
+            None
+        } else {
+            Some(&mesh.colors[i0] * b0 + &mesh.colors[i1] * b1 + &mesh.colors[i2] * b2)
+        };
+
+        let (u, v, dpdu, dpdv) = if mesh.uvs.is_empty() {
+            (b1, b2, v1 - v0, v2 - v0)
+        } else {
+            let (uv0, uv1, uv2) = (mesh.uvs[i0], mesh.uvs[i1], mesh.uvs[i2]);
+            let (dpdu, dpdv) = uv_tangents([v0, v1, v2], [uv0, uv1, uv2]);
+            (
+                uv0.0 * b0 + uv1.0 * b1 + uv2.0 * b2,
+                uv0.1 * b0 + uv1.1 * b1 + uv2.1 * b2,
+                dpdu,
+                dpdv,
+            )
+        };
+
+        HitRecord {
+            p: ray.at(t),
+            normal: if front_face { outward_normal } else { -outward_normal },
+            material: mesh.material.deref(),
+            t,
+            u,
+            v,
+            dpdu,
+            dpdv,
+            vertex_color,
+            front_face,
+            is_hit: true,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [v0, v1, v2] = self.positions();
+        triangle_bounding_box(v0, v1, v2)
+    }
+}
+
+// Solves for the edges in terms of the differences in uv across them. When the
+// uvs leave no area, the edges from the first vertex stand in.
+fn uv_tangents([p0, p1, p2]: [&Point3; 3], [uv0, uv1, uv2]: [(f32, f32); 3]) -> (Vec3, Vec3) {
+    let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+    let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+    let determinant = du02 * dv12 - dv02 * du12;
+    if determinant.abs() < 1e-12 {
+        return (p1 - p0, p2 - p0);
+    }
+
+    let (dp02, dp12) = (p0 - p2, p1 - p2);
+    let dpdu = (&dp02 * dv12 - &dp12 * dv02) / determinant;
+    let dpdv = (&dp12 * du02 - &dp02 * du12) / determinant;
+    (dpdu, dpdv)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::{Point3, Vec3};
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::triangle::Triangle;
+    use crate::triangle_mesh::{MeshError, TriangleMesh};
+    use crate::utils::seed_rng;
+
+    fn material() -> Box<Lambertian> {
+        Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    // A unit square in the z = 0 plane, facing +z, split into two triangles.
+    fn square(normals: Vec<Vec3>) -> TriangleMesh<'static> {
+        TriangleMesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals,
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![[0, 1, 2], [0, 2, 3]],
+            material(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn mesh_interpolates_uvs_and_faces_the_winding_order() {
+        let mesh = square(vec![]);
+        let rec = mesh.hit(&Ray::new(Point3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+
+        assert!(rec.is_hit);
+        assert!(rec.front_face);
+        assert!((rec.t - 1.0).abs() < 1e-5);
+        assert!((rec.u - 0.25).abs() < 1e-5 && (rec.v - 0.75).abs() < 1e-5);
+        assert!((&rec.normal - &Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        // The uvs run along x and y, and so do the tangents.
+        assert!((&rec.dpdu - &Vec3::new(1.0, 0.0, 0.0)).length() < 1e-5);
+        assert!((&rec.dpdv - &Vec3::new(0.0, 1.0, 0.0)).length() < 1e-5);
+
+        let rec = mesh.hit(&Ray::new(Point3::new(0.5, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0)), 0.001, f32::INFINITY);
+        assert!(rec.is_hit);
+        assert!(!rec.front_face);
+        assert!((&rec.normal - &Vec3::new(0.0, 0.0, -1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn arrays_that_do_not_fit_together_are_errors() {
+        let positions = || vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+
+        let error = TriangleMesh::new(positions(), vec![Vec3::new(0.0, 0.0, 1.0)], vec![], vec![[0, 1, 2]], material());
+        assert!(matches!(error, Err(MeshError::MismatchedArray { name: "normals", length: 1, vertex_count: 3 })));
+        let error = TriangleMesh::new(positions(), vec![], vec![(0.0, 0.0); 4], vec![[0, 1, 2]], material());
+        assert!(matches!(error, Err(MeshError::MismatchedArray { name: "UVs", length: 4, vertex_count: 3 })));
+        let error = TriangleMesh::colored(positions(), vec![], vec![], vec![Color::origin()], vec![[0, 1, 2]], material());
+        assert!(matches!(error, Err(MeshError::MismatchedArray { name: "colours", .. })));
+        let error = TriangleMesh::new(positions(), vec![], vec![], vec![[0, 1, 3]], material());
+        assert!(matches!(error, Err(MeshError::MissingVertex(3))));
+    }
+
+    #[test]
+    fn mesh_interpolates_shading_normals() {
+        let mesh = square(vec![
+            Vec3::new(-1.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(-1.0, 0.0, 1.0),
+        ]);
+        let rec = mesh.hit(&Ray::new(Point3::new(0.5, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f32::INFINITY);
+
+        assert!(rec.is_hit);
+        assert!((&rec.normal - &Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn mesh_and_single_triangles_agree() {
+        let mesh = square(vec![]);
+        let triangles = [
+            Triangle::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0), material()),
+            Triangle::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0), Point3::new(0.0, 1.0, 0.0), material()),
+        ];
+
+        for i in 0..100 {
+            let target = Point3::new(-0.2 + 1.4 * (i % 10) as f32 / 9.0, -0.2 + 1.4 * (i / 10) as f32 / 9.0, 0.0);
+            let ray = Ray::new(Point3::new(0.3, 0.4, 2.0), &target - &Point3::new(0.3, 0.4, 2.0));
+
+            let expected = triangles.iter()
+                .map(|triangle| triangle.hit(&ray, 0.001, f32::INFINITY))
+                .find(|rec| rec.is_hit);
+            let actual = mesh.hit(&ray, 0.001, f32::INFINITY);
+
+            assert_eq!(expected.is_some(), actual.is_hit);
+            if let Some(expected) = expected {
+                assert!((expected.t - actual.t).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn light_density_integrates_to_one_through_overlapping_triangles() {
+        seed_rng(12);
+        // A small square above a larger one, so some directions pass through both.
+        let mesh = TriangleMesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+                Point3::new(-1.0, -1.0, -1.0),
+                Point3::new(2.0, -1.0, -1.0),
+                Point3::new(2.0, 2.0, -1.0),
+                Point3::new(-1.0, 2.0, -1.0),
+            ],
+            vec![],
+            vec![],
+            vec![[0, 1, 2], [0, 2, 3], [4, 5, 6], [4, 6, 7]],
+            material(),
+        )
+        .unwrap();
+        let origin = Point3::new(0.3, 0.6, 1.5);
+
+        let samples = 400_000;
+        let total: f32 = (0..samples)
+            .map(|_| mesh.pdf_value(&origin, &Vec3::random_unit_vector()))
+            .sum();
+        let integral = total / samples as f32 * 4.0 * PI;
+        assert!((integral - 1.0).abs() < 0.02, "integral {}", integral);
+
+        for _ in 0..100 {
+            let direction = mesh.random_direction(&origin);
+            assert!(mesh.hit(&Ray::new(origin.clone(), direction.clone()), 0.001, f32::INFINITY).is_hit);
+            assert!(mesh.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
+}
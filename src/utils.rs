@@ -1,7 +1,28 @@
+use std::cell::RefCell;
 use std::f32::consts::PI;
 
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+thread_local! {
+    // Each render thread draws from its own generator so sampling never contends on a lock.
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Restarts the current thread's generator from `seed`, making every draw that
+/// follows on this thread reproducible.
+pub fn seed_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Derives the seed of an independent random stream, such as a single pixel,
+/// from a base seed.
+pub fn stream_seed(seed: u64, stream: u64) -> u64 {
+    seed ^ stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
 pub fn random_float() -> f32 {
-    rand::random::<f32>()
+    RNG.with(|rng| rng.borrow_mut().gen::<f32>())
 }
 
 pub fn random_float_range(min: f32, max: f32) -> f32 {
@@ -20,4 +41,11 @@ pub fn clamp(x: f32, min: f32, max: f32) -> f32 {
 
 pub fn degrees_to_radians(degrees: f32) -> f32 {
     degrees * PI / 180.0
-}
\ No newline at end of file
+}
+
+/// A file in the tests' fixtures directory, or the directory itself for an
+/// empty name.
+#[cfg(test)]
+pub(crate) fn fixture(name: &str) -> std::path::PathBuf {
+    std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
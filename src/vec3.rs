@@ -1,3 +1,4 @@
+use std::f32::consts::PI;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub};
@@ -47,9 +48,9 @@ impl Vec3 {
 
     pub fn cross(&self, u: &Vec3) -> Vec3 {
         Vec3::new(
-            u.e[1] * self.e[2] - u.e[2] * self.e[1],
-            u.e[2] * self.e[0] - u.e[0] * self.e[2],
-            u.e[0] * self.e[1] - u.e[1] * self.e[0],
+            self.e[1] * u.e[2] - self.e[2] * u.e[1],
+            self.e[2] * u.e[0] - self.e[0] * u.e[2],
+            self.e[0] * u.e[1] - self.e[1] * u.e[0],
         )
     }
 
@@ -83,9 +84,7 @@ impl Vec3 {
 
     pub fn near_zero(&self) -> bool {
         // Return true if the vector is close to zero in all dimensions.
-        self.e.iter()
-            .map(|item| item < &S)
-            .fold(true, |accum, item| accum && item)
+        self.e.iter().all(|item| item.abs() < S)
     }
 
     pub fn reflect(&self, n: &Vec3) -> Vec3 {
@@ -99,6 +98,19 @@ impl Vec3 {
         r_out_perp + r_out_parallel
     }
 
+    /// A random direction spread uniformly over the cone of directions within
+    /// `acos(cos_theta_max)` of the z axis.
+    pub fn random_in_cone(cos_theta_max: f32) -> Vec3 {
+        let r1 = random_float();
+        let r2 = random_float();
+        let z = 1.0 + r2 * (cos_theta_max - 1.0);
+
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+
+        Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
+    }
+
     pub fn random_in_unit_disc() -> Vec3 {
         loop {
             let p = Vec3::new(
@@ -262,3 +274,27 @@ impl Div<f32> for &Vec3 {
         self * (1.0 / rhs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::Vec3;
+
+    #[test]
+    fn near_zero_looks_at_magnitudes() {
+        assert!(Vec3::new(1e-9, -1e-9, 0.0).near_zero());
+        assert!(!Vec3::new(-1.0, -2.0, -3.0).near_zero());
+        assert!(!Vec3::new(0.0, 0.0, -0.5).near_zero());
+    }
+
+    #[test]
+    fn cross_is_right_handed() {
+        let cross = |a: Vec3, b: Vec3| {
+            let c = a.cross(&b);
+            [c.x(), c.y(), c.z()]
+        };
+        assert_eq!(cross(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)), [0.0, 0.0, 1.0]);
+        assert_eq!(cross(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)), [1.0, 0.0, 0.0]);
+        assert_eq!(cross(Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0)), [0.0, 1.0, 0.0]);
+        assert_eq!(cross(Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0)), [-3.0, 6.0, -3.0]);
+    }
+}